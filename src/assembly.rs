@@ -0,0 +1,248 @@
+use regex::Regex;
+use wikibase::*;
+
+pub const GENOMIC_ASSEMBLY_Q: &str = "Q7307127";
+pub const GCA_PROPERTY: &str = "P4333"; // GenBank assembly accession
+
+/// Version and accession of the genome assembly the GFF refers to
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssemblyInfo {
+    pub version: Option<String>,
+    pub accession: Option<String>,
+    /// Wikidata time value, from "#!genome-date"
+    pub date: Option<(String, u64)>,
+}
+
+impl AssemblyInfo {
+    pub fn new() -> Self {
+        Self {
+            version: None,
+            accession: None,
+            date: None,
+        }
+    }
+
+    /// Reads the "#!genome-version", "#!genome-build", "#!genome-build-accession" and "#!genome-date" GFF pragmas
+    pub fn update_from_gff_header_line(&mut self, line: &str) {
+        lazy_static! {
+            static ref RE_GCA: Regex = Regex::new(r"\b(GCA_\d+(\.\d+)?)\b")
+                .expect("update_from_gff_header_line: RE_GCA does not compile");
+            static ref RE_DATE: Regex = Regex::new(r"^(\d{4})-(\d{2})(-(\d{2}))?$")
+                .expect("update_from_gff_header_line: RE_DATE does not compile");
+        }
+        let mut parts = line.trim().splitn(2, char::is_whitespace);
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => (key, value.trim()),
+            _ => return,
+        };
+        if value.is_empty() {
+            return;
+        }
+        match key {
+            "#!genome-version" => self.version = Some(value.to_string()),
+            "#!genome-build" => {
+                if self.version.is_none() {
+                    self.version = Some(value.to_string())
+                }
+            }
+            "#!genome-build-accession" => match RE_GCA.captures(value) {
+                Some(c) => self.accession = Some(c[1].to_string()),
+                None => {}
+            },
+            "#!genome-date" => match RE_DATE.captures(value) {
+                Some(c) => {
+                    self.date = match c.get(4) {
+                        Some(day) => Some((
+                            format!("+{}-{}-{}T00:00:00Z", &c[1], &c[2], day.as_str()),
+                            11,
+                        )),
+                        None => Some((format!("+{}-{}-00T00:00:00Z", &c[1], &c[2]), 10)),
+                    }
+                }
+                None => {}
+            },
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none() && self.accession.is_none()
+    }
+
+    /// Version, accession and date statements for the assembly item
+    pub fn snaks(&self) -> Vec<Snak> {
+        let mut ret = vec![];
+        match &self.version {
+            Some(version) => ret.push(Snak::new_string("P348", version)),
+            None => {}
+        }
+        match &self.accession {
+            Some(accession) => ret.push(Snak::new_string(GCA_PROPERTY, accession)),
+            None => {}
+        }
+        match &self.date {
+            Some((time, precision)) => ret.push(Snak::new_time("P577", time, *precision)),
+            None => {}
+        }
+        ret
+    }
+}
+
+/// An existing genomic assembly item of the species
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssemblyCandidate {
+    pub q: String,
+    pub versions: Vec<String>,
+    pub accessions: Vec<String>,
+    /// Has a "replaced by" (P1366) statement
+    pub is_replaced: bool,
+}
+
+impl AssemblyCandidate {
+    pub fn new_from_entity(item: &Entity) -> Self {
+        let strings = |property: &str| -> Vec<String> {
+            item.values_for_property(property)
+                .iter()
+                .filter_map(|v| match v {
+                    Value::StringValue(s) => Some(s.to_owned()),
+                    _ => None,
+                })
+                .collect()
+        };
+        Self {
+            q: item.id().to_string(),
+            versions: strings("P348"),
+            accessions: strings(GCA_PROPERTY),
+            is_replaced: !item.values_for_property("P1366").is_empty(),
+        }
+    }
+
+    fn is_unversioned(&self) -> bool {
+        self.versions.is_empty() && self.accessions.is_empty()
+    }
+}
+
+/// Which assembly item to use for the current release
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssemblyChoice {
+    /// The item for this assembly exists
+    Existing(String),
+    /// The only, unversioned item gets the version of this assembly
+    Adopt(String),
+    /// A new item is needed, replacing the given items
+    New(Vec<String>),
+    Ambiguous,
+}
+
+pub fn choose_assembly(candidates: &Vec<AssemblyCandidate>, info: &AssemblyInfo) -> AssemblyChoice {
+    let current: Vec<&AssemblyCandidate> = candidates.iter().filter(|c| !c.is_replaced).collect();
+    if info.is_empty() {
+        return match (candidates.len(), current.len()) {
+            (0, _) => AssemblyChoice::New(vec![]),
+            (1, _) => AssemblyChoice::Existing(candidates[0].q.to_owned()),
+            (_, 1) => AssemblyChoice::Existing(current[0].q.to_owned()),
+            _ => AssemblyChoice::Ambiguous,
+        };
+    }
+
+    let matches: Vec<&AssemblyCandidate> = match &info.accession {
+        Some(accession) => candidates
+            .iter()
+            .filter(|c| c.accessions.contains(accession))
+            .collect(),
+        None => vec![],
+    };
+    let matches = match (&info.version, matches.is_empty()) {
+        (Some(version), true) => candidates
+            .iter()
+            .filter(|c| c.versions.contains(version))
+            .collect(),
+        _ => matches,
+    };
+    match matches.len() {
+        0 => {}
+        1 => return AssemblyChoice::Existing(matches[0].q.to_owned()),
+        _ => return AssemblyChoice::Ambiguous,
+    }
+
+    if candidates.len() == 1 && candidates[0].is_unversioned() {
+        return AssemblyChoice::Adopt(candidates[0].q.to_owned());
+    }
+    AssemblyChoice::New(current.iter().map(|c| c.q.to_owned()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(q: &str, version: &str, accession: &str, is_replaced: bool) -> AssemblyCandidate {
+        let to_vec = |s: &str| -> Vec<String> {
+            if s.is_empty() {
+                vec![]
+            } else {
+                vec![s.to_string()]
+            }
+        };
+        AssemblyCandidate {
+            q: q.to_string(),
+            versions: to_vec(version),
+            accessions: to_vec(accession),
+            is_replaced,
+        }
+    }
+
+    fn info(version: &str, accession: &str) -> AssemblyInfo {
+        let mut info = AssemblyInfo::new();
+        info.update_from_gff_header_line(&format!("#!genome-version {}", version));
+        info.update_from_gff_header_line(&format!("#!genome-build-accession {}", accession));
+        info
+    }
+
+    #[test]
+    fn test_update_from_gff_header_line() {
+        let mut info = AssemblyInfo::new();
+        info.update_from_gff_header_line("#!genome-build GeneDB 2019-10");
+        info.update_from_gff_header_line("#!genome-build-accession NCBI:GCA_000002765.3");
+        info.update_from_gff_header_line("#!genome-date 2015-11");
+        info.update_from_gff_header_line("##gff-version 3");
+        assert_eq!(info.version, Some("GeneDB 2019-10".to_string()));
+        assert_eq!(info.accession, Some("GCA_000002765.3".to_string()));
+        assert_eq!(info.date, Some(("+2015-11-00T00:00:00Z".to_string(), 10)));
+        info.update_from_gff_header_line("#!genome-version 3");
+        assert_eq!(info.version, Some("3".to_string()));
+        assert_eq!(info.snaks().len(), 3);
+    }
+
+    #[test]
+    fn test_choose_assembly() {
+        let none = AssemblyInfo::new();
+        assert_eq!(choose_assembly(&vec![], &none), AssemblyChoice::New(vec![]));
+        let old = candidate("Q1", "2", "GCA_1.2", true);
+        let current = candidate("Q2", "3", "GCA_1.3", false);
+        let candidates = vec![old.clone(), current.clone()];
+        assert_eq!(
+            choose_assembly(&candidates, &none),
+            AssemblyChoice::Existing("Q2".to_string())
+        );
+        assert_eq!(
+            choose_assembly(&candidates, &info("x", "GCA_1.2")),
+            AssemblyChoice::Existing("Q1".to_string())
+        );
+        assert_eq!(
+            choose_assembly(&candidates, &info("3", "")),
+            AssemblyChoice::Existing("Q2".to_string())
+        );
+        assert_eq!(
+            choose_assembly(&candidates, &info("4", "GCA_1.4")),
+            AssemblyChoice::New(vec!["Q2".to_string()])
+        );
+        assert_eq!(
+            choose_assembly(&vec![candidate("Q3", "", "", false)], &info("4", "")),
+            AssemblyChoice::Adopt("Q3".to_string())
+        );
+        assert_eq!(
+            choose_assembly(&vec![current.clone(), current], &info("3", "")),
+            AssemblyChoice::Ambiguous
+        );
+    }
+}
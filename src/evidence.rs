@@ -1,18 +1,114 @@
 use std::collections::HashMap;
 use std::error::Error;
 
+/// GO evidence codes, their labels (as used in GFF evidence strings), and their ECO IDs
+const GO_EVIDENCE_CODES: &[(&str, &str, &str)] = &[
+    ("EXP", "inferred from experiment", "ECO:0000269"),
+    ("IDA", "inferred from direct assay", "ECO:0000314"),
+    ("IPI", "inferred from physical interaction", "ECO:0000353"),
+    ("IMP", "inferred from mutant phenotype", "ECO:0000315"),
+    ("IGI", "inferred from genetic interaction", "ECO:0000316"),
+    ("IEP", "inferred from expression pattern", "ECO:0000270"),
+    (
+        "HTP",
+        "inferred from high throughput experiment",
+        "ECO:0006056",
+    ),
+    (
+        "HDA",
+        "inferred from high throughput direct assay",
+        "ECO:0007005",
+    ),
+    (
+        "HMP",
+        "inferred from high throughput mutant phenotype",
+        "ECO:0007001",
+    ),
+    (
+        "HGI",
+        "inferred from high throughput genetic interaction",
+        "ECO:0007003",
+    ),
+    (
+        "HEP",
+        "inferred from high throughput expression pattern",
+        "ECO:0007007",
+    ),
+    (
+        "ISS",
+        "inferred from sequence or structural similarity",
+        "ECO:0000250",
+    ),
+    ("ISO", "inferred from sequence orthology", "ECO:0000266"),
+    ("ISA", "inferred from sequence alignment", "ECO:0000247"),
+    ("ISM", "inferred from sequence model", "ECO:0000255"),
+    ("IGC", "inferred from genomic context", "ECO:0000317"),
+    (
+        "IBA",
+        "inferred from biological aspect of ancestor",
+        "ECO:0000318",
+    ),
+    (
+        "IBD",
+        "inferred from biological aspect of descendant",
+        "ECO:0000319",
+    ),
+    ("IKR", "inferred from key residues", "ECO:0000320"),
+    ("IRD", "inferred from rapid divergence", "ECO:0000321"),
+    (
+        "RCA",
+        "inferred from reviewed computational analysis",
+        "ECO:0000245",
+    ),
+    ("TAS", "traceable author statement", "ECO:0000304"),
+    ("NAS", "non-traceable author statement", "ECO:0000303"),
+    ("IC", "inferred by curator", "ECO:0000305"),
+    ("ND", "no biological data available", "ECO:0000307"),
+    ("IEA", "inferred from electronic annotation", "ECO:0000501"),
+];
+
+/// How an evidence item was found
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvidenceMatch {
+    /// Via the ECO ID (P3811) of the item
+    Eco(String),
+    /// Via the label or alias of the item only
+    Label(String),
+}
+
+impl EvidenceMatch {
+    pub fn item(&self) -> String {
+        match self {
+            EvidenceMatch::Eco(q) => q.to_string(),
+            EvidenceMatch::Label(q) => q.to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Evidence {
     pub label2q: HashMap<String, String>,
     pub code2q: HashMap<String, String>,
+    pub eco2q: HashMap<String, String>,
+    pub code2eco: HashMap<String, String>,
+    pub label2eco: HashMap<String, String>,
 }
 
 impl Evidence {
     pub fn new() -> Self {
-        Self {
+        let mut ret = Self {
             label2q: HashMap::new(),
             code2q: HashMap::new(),
+            eco2q: HashMap::new(),
+            code2eco: HashMap::new(),
+            label2eco: HashMap::new(),
+        };
+        for (code, label, eco) in GO_EVIDENCE_CODES {
+            ret.code2eco.insert(code.to_string(), eco.to_string());
+            ret.label2eco
+                .insert(ret.normalize_label(&label.to_string()), eco.to_string());
         }
+        ret
     }
 
     pub fn load_from_wikidata(
@@ -36,9 +132,78 @@ impl Evidence {
                             .insert(self.normalize_label(&alt_label), q.clone());
                         self.code2q.insert(label, q.clone());
                     });
+            }
+            None => return Err(From::from(format!("load_from_wikidata failed"))),
+        }
+        self.load_eco_from_wikidata(api)
+    }
+
+    /// Loads all items with an ECO ID
+    fn load_eco_from_wikidata(
+        self: &mut Self,
+        api: &mut wikibase::mediawiki::api::Api,
+    ) -> Result<(), Box<dyn Error>> {
+        let sparql = "SELECT DISTINCT ?q ?eco { ?q wdt:P3811 ?eco }";
+        let res = api.sparql_query(&sparql)?;
+        match res["results"]["bindings"].as_array() {
+            Some(bindings) => {
+                bindings
+                    .iter()
+                    .filter(|b| b["q"]["value"].as_str().is_some())
+                    .filter(|b| b["eco"]["value"].as_str().is_some())
+                    .for_each(|b| {
+                        let q = b["q"]["value"].as_str().unwrap();
+                        let q = api.extract_entity_from_uri(q).unwrap().to_string();
+                        let eco = b["eco"]["value"].as_str().unwrap();
+                        self.eco2q.insert(self.normalize_eco(eco), q);
+                    });
                 Ok(())
             }
-            None => Err(From::from(format!("load_from_wikidata failed"))),
+            None => Err(From::from(format!("load_eco_from_wikidata failed"))),
+        }
+    }
+
+    /// Returns the ECO ID for a GO evidence code ("IEA"), a GFF evidence label, or an ECO ID
+    pub fn get_eco(&self, evidence: &str) -> Option<String> {
+        let evidence = evidence.trim();
+        if evidence.starts_with("ECO:") || evidence.starts_with("ECO_") {
+            return Some(self.normalize_eco(evidence));
+        }
+        match self.code2eco.get(evidence) {
+            Some(eco) => Some(eco.to_string()),
+            None => self
+                .label2eco
+                .get(&self.normalize_label(&evidence.to_string()))
+                .map(|eco| eco.to_string()),
+        }
+    }
+
+    /// Returns the item for a GO evidence code, a GFF evidence label, or an ECO ID.
+    /// Tries the ECO mapping first, then falls back to item labels and aliases.
+    pub fn get_item(&self, evidence: &str) -> Option<EvidenceMatch> {
+        match self.get_eco(evidence) {
+            Some(eco) => match self.eco2q.get(&eco) {
+                Some(q) => return Some(EvidenceMatch::Eco(q.to_string())),
+                None => {}
+            },
+            None => {}
+        }
+        match self.code2q.get(evidence.trim()) {
+            Some(q) => return Some(EvidenceMatch::Label(q.to_string())),
+            None => {}
+        }
+        self.label2q
+            .get(&self.normalize_label(&evidence.to_string()))
+            .map(|q| EvidenceMatch::Label(q.to_string()))
+    }
+
+    /// Normalizes ECO IDs to "ECO:0000000"
+    pub fn normalize_eco(&self, s: &str) -> String {
+        let s = s.trim();
+        if s.starts_with("ECO:") {
+            s.to_string()
+        } else {
+            format!("ECO:{}", s.trim_start_matches("ECO_"))
         }
     }
 
@@ -56,6 +221,7 @@ mod tests {
         let e = Evidence::new();
         assert!(e.label2q.is_empty());
         assert!(e.code2q.is_empty());
+        assert_eq!(e.code2eco.get("IEA"), Some(&"ECO:0000501".to_string()));
     }
 
     #[test]
@@ -67,6 +233,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_eco() {
+        let e = Evidence::new();
+        assert_eq!(e.get_eco("ISA"), Some("ECO:0000247".to_string()));
+        assert_eq!(
+            e.get_eco(" Inferred by Curator "),
+            Some("ECO:0000305".to_string())
+        );
+        assert_eq!(e.get_eco("ECO_0000247"), Some("ECO:0000247".to_string()));
+        assert_eq!(e.get_eco("no such thing"), None);
+        assert_eq!(e.normalize_eco("0000247"), "ECO:0000247");
+    }
+
+    #[test]
+    fn test_get_item() {
+        let mut e = Evidence::new();
+        e.eco2q
+            .insert("ECO:0000247".to_string(), "Q23190738".to_string());
+        e.code2q.insert("IDA".to_string(), "Q23190735".to_string());
+        e.label2q
+            .insert("some other evidence".to_string(), "Q1".to_string());
+        assert_eq!(
+            e.get_item("ISA"),
+            Some(EvidenceMatch::Eco("Q23190738".to_string()))
+        );
+        assert_eq!(
+            e.get_item("inferred from sequence alignment"),
+            Some(EvidenceMatch::Eco("Q23190738".to_string()))
+        );
+        assert_eq!(
+            e.get_item("ECO:0000247"),
+            Some(EvidenceMatch::Eco("Q23190738".to_string()))
+        );
+        assert_eq!(
+            e.get_item("IDA"),
+            Some(EvidenceMatch::Label("Q23190735".to_string()))
+        );
+        assert_eq!(
+            e.get_item("Some other evidence"),
+            Some(EvidenceMatch::Label("Q1".to_string()))
+        );
+        assert_eq!(e.get_item("ECO:0000000"), None);
+    }
+
     #[test]
     fn test_load_from_wikidata() {
         let mut api =
@@ -78,6 +288,7 @@ mod tests {
             Some(&"Q23190856".to_string())
         );
         assert_eq!(e.code2q.get("ISA"), Some(&"Q23190738".to_string()));
+        assert_eq!(e.eco2q.get("ECO:0000305"), Some(&"Q23190856".to_string()));
     }
 
 }
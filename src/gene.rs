@@ -1,5 +1,9 @@
+use crate::genedbot::GoRollup;
+use crate::go_ontology::GoTermStatus;
+use crate::protection::ItemKind;
+use crate::transcript;
 use crate::{GeneDBot, Literature, Toolbox};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use wikibase::entity_diff::*;
 use wikibase::*;
 
@@ -33,14 +37,14 @@ pub fn process(bot: &mut GeneDBot, genedb_id: String) {
         }
     };
 
-    // Labels and aliases
-    match gff.attributes().get("Name") {
-        Some(name) => {
-            item.set_label(LocaleString::new("en", name));
-            //item.add_alias(LocaleString::new("en", &bot.fix_alias_name(&genedb_id)));
-        }
-        None => item.set_label(LocaleString::new("en", &genedb_id)),
+    // Labels and aliases in English; other languages keep their labels and aliases, or fall back to the English label
+    let languages = bot.languages();
+    let label = match gff.attributes().get("Name") {
+        Some(name) => name.to_string(),
+        None => genedb_id.to_string(),
     };
+    item.set_label(LocaleString::new("en", &label));
+    //item.add_alias(LocaleString::new("en", &bot.fix_alias_name(&genedb_id)));
     vec!["previous_systematic_id", "synonym", "alias"]
         .iter()
         .for_each(|key| {
@@ -51,12 +55,10 @@ pub fn process(bot: &mut GeneDBot, genedb_id: String) {
                 None => {}
             };
         });
+    bot.set_extra_language_labels(&item_to_diff, &mut item, &label);
 
     // Statements
-    let reference = Reference::new(vec![
-        Snak::new_item("P248", "Q5531047"),
-        bot.new_time_today(),
-    ]);
+    let reference = bot.genedb_reference(&genedb_id);
     let ga_quals = vec![
         Snak::new_item("P659", &bot.genomic_assembly_q),
         Snak::new_item("P1057", &chr_q),
@@ -92,7 +94,25 @@ pub fn process(bot: &mut GeneDBot, genedb_id: String) {
         vec![reference.clone()],
     ));
 
+    let mut description_type = gene_type.0.to_string();
     let protein_entity_ids = bot.process_proteins(&genedb_id);
+
+    // Has part:transcript, with the isoform number
+    let mut transcript_entity_ids = vec![];
+    if bot.transcript_items {
+        for (transcript_q, isoform) in transcript::process_transcripts(bot, &genedb_id) {
+            let qualifiers = match isoform {
+                Some(isoform) => vec![Snak::new_string("P1545", &isoform.to_string())],
+                None => vec![],
+            };
+            item.add_claim(Statement::new_normal(
+                Snak::new_item("P527", &transcript_q),
+                qualifiers,
+                vec![reference.clone()],
+            ));
+            transcript_entity_ids.push(transcript_q);
+        }
+    }
     if protein_entity_ids.len() > 0 {
         if gene_type.0 == "gene" {
             // Genes only, no pseudogene
@@ -105,6 +125,9 @@ pub fn process(bot: &mut GeneDBot, genedb_id: String) {
         for protein_q in &protein_entity_ids {
             statements_to_create.push(Snak::new_item("P688", &protein_q));
         }
+        if gene_type.0 == "gene" {
+            description_type = "protein_coding_gene".to_string();
+        }
     } else if gene_type.0 != "gene" {
         statements_to_create.push(Snak::new_item("P279", gene_type.1));
     } else {
@@ -121,6 +144,9 @@ pub fn process(bot: &mut GeneDBot, genedb_id: String) {
                     Some(gff_tmp) => {
                         statements_to_create.push(Snak::new_item("P279", &class_q));
                         subclass_found = true;
+                        if class_name != "pseudogene" {
+                            description_type = format!("{}_gene", class_name);
+                        }
                         let mut fake_literature: HashSet<Literature> = HashSet::new();
                         bot.process_product(
                             &gff_tmp,
@@ -144,16 +170,23 @@ pub fn process(bot: &mut GeneDBot, genedb_id: String) {
     }
 
     // Orthologs
-    if !protein_entity_ids.is_empty() {
+    let mut ortholog_entity_ids: Vec<String> = vec![];
+    if !protein_entity_ids.is_empty() && bot.orthologs.on_genes() {
         match bot.parent2child.get(&genedb_id) {
             Some(child) => {
-                bot.orthologs
+                ortholog_entity_ids = bot
+                    .orthologs
                     .process(&child, &bot.gff, &mut item, &reference);
             }
             None => {}
         }
     }
 
+    // GO terms from proteins
+    if !protein_entity_ids.is_empty() {
+        add_go_rollup(bot, &genedb_id, &mut item);
+    }
+
     // Create simple (single-reference-only) statements
     statements_to_create.iter().for_each(|s| {
         item.add_claim(Statement::new_normal(
@@ -164,34 +197,47 @@ pub fn process(bot: &mut GeneDBot, genedb_id: String) {
     });
 
     // Apply diff
-    let my_props = vec![
+    let paralog_property = bot.orthologs.paralog_property.clone();
+    let mut my_props = vec![
         "P279",  // Subclass of (mostly to remove protein-coding gene)
         "P703",  // Found in taxon
         "P680",  // Molecular function
         "P681",  // Cell component
         "P682",  // Biological process
-        "P684",  // Ortholog
         "P1057", // Chromosome
         "P2548", // Strand orientation
         "P644",  // Genomic start
         "P645",  // Genomic end
         "P688",  // Encodes
     ];
+    if bot.transcript_items {
+        my_props.push("P527"); // Has part (transcripts)
+    }
+    if bot.orthologs.on_genes() {
+        my_props.push("P684"); // Ortholog
+        if bot.orthologs.process_paralogs {
+            my_props.push(&paralog_property);
+        }
+    }
 
+    let languages: Vec<&str> = languages.iter().map(|l| l.as_str()).collect();
     let mut params = EntityDiffParams::none();
-    params.labels = EntityDiffParam::some(&vec!["en"]);
-    params.descriptions = EntityDiffParam::some(&vec!["en"]);
-    params.aliases = EntityDiffParam::some(&vec!["en"]);
+    params.labels = EntityDiffParam::some(&languages);
+    params.descriptions = EntityDiffParam::some(&languages);
+    params.aliases = EntityDiffParam::some(&languages);
     params.claims.add = EntityDiffParamState::All;
     params.claims.alter = EntityDiffParamState::All;
     params.claims.remove = EntityDiffParamState::some(&my_props);
     params.references.list.push((
         EntityDiffParamState::some(&my_props),
-        EntityDiffParamState::except(&vec!["P813"]),
+        bot.retrieved_policy.reference_diff_state(),
     ));
 
+    bot.set_description(&item_to_diff, &mut item, &description_type, &genedb_id);
+    bot.apply_retrieved_policy(&item_to_diff, &mut item);
     let mut diff = EntityDiff::new(&item_to_diff, &item, &params);
     diff.set_edit_summary(bot.get_edit_summary());
+    bot.protect_claims(ItemKind::Gene, &mut diff, &item_to_diff);
     if !diff.is_empty() {
         if bot.verbose {
             println!(
@@ -217,11 +263,151 @@ pub fn process(bot: &mut GeneDBot, genedb_id: String) {
             for protein_q in protein_entity_ids {
                 link_protein_to_gene(bot, &protein_q, &gene_q);
             }
+            for transcript_q in transcript_entity_ids {
+                link_transcript_to_gene(bot, &transcript_q, &gene_q);
+            }
+            if bot.orthologs.check_reverse() {
+                link_orthologs(bot, &genedb_id, &ortholog_entity_ids, &gene_q);
+            }
         }
         None => {}
     }
 }
 
+/// Summarises the GO annotations of the proteins onto the gene item, either as-is or mapped to the GO slim.
+/// Each statement has one reference per protein it was inferred from (P3452).
+fn add_go_rollup(bot: &mut GeneDBot, genedb_id: &String, item: &mut Entity) {
+    if bot.go_rollup == GoRollup::None {
+        return;
+    }
+    let protein_genedb_ids: Vec<String> = bot
+        .parent2child
+        .get(genedb_id)
+        .unwrap_or(&vec![])
+        .iter()
+        .filter(|child| bot.is_product_type(&child.1))
+        .map(|child| child.0.to_owned())
+        .collect();
+
+    // (aspect property, GO term) => protein items
+    let mut term2proteins: HashMap<(String, String), Vec<String>> = HashMap::new();
+    for protein_genedb_id in protein_genedb_ids {
+        let protein_q = match bot.get_entity_id_for_genedb_id(&protein_genedb_id) {
+            Some(q) => q,
+            None => continue,
+        };
+        let gaf = match bot.gaf.get(&protein_genedb_id) {
+            Some(gaf) => gaf.clone(),
+            None => continue,
+        };
+        for ga in gaf {
+            if ga.qualifier().iter().any(|q| q == "NOT") {
+                continue;
+            }
+            let aspect_p = match bot.aspects.get(ga.aspect()) {
+                Some(p) => p.to_string(),
+                None => continue,
+            };
+            // Obsolete terms are reported for the protein already
+            let go_term = match bot.go_ontology.get_status(ga.go_id()) {
+                GoTermStatus::Current(term) => term,
+                GoTermStatus::Replaced(term) => term,
+                GoTermStatus::Obsolete(_) => continue,
+            };
+            let terms = match bot.go_rollup {
+                GoRollup::Slim => bot.go_ontology.get_slim_terms(&go_term, &bot.go_slim),
+                _ => vec![go_term],
+            };
+            for term in terms {
+                let proteins = term2proteins
+                    .entry((aspect_p.clone(), term))
+                    .or_insert(vec![]);
+                if !proteins.contains(&protein_q) {
+                    proteins.push(protein_q.clone());
+                }
+            }
+        }
+    }
+
+    let mut keys: Vec<(String, String)> = term2proteins.keys().cloned().collect();
+    keys.sort();
+    for key in keys {
+        let (aspect_p, go_term) = &key;
+        let go_q = match bot.get_item_for_go_term(go_term) {
+            Some(q) => q,
+            None => {
+                bot.log(
+                    genedb_id,
+                    &format!("No Wikidata item for GO term '{}'", go_term),
+                );
+                continue;
+            }
+        };
+        let references = term2proteins[&key]
+            .iter()
+            .map(|protein_q| {
+                let mut snaks = vec![Snak::new_item("P3452", protein_q)]; // Inferred from
+                snaks.extend(bot.genedb_reference(genedb_id).snaks().iter().cloned());
+                Reference::new(snaks)
+            })
+            .collect();
+        item.add_claim(Statement::new_normal(
+            Snak::new_item(aspect_p, &go_q),
+            vec![],
+            references,
+        ));
+    }
+}
+
+/// Adds the reverse P684 to ortholog items (symmetric mode), and/or reports one-directional pairs.
+/// Reverse links are referenced as inferred from (P3452) the item, not as stated in GeneDB, so they are protected from removal.
+/// Works for both gene and protein items.
+pub fn link_orthologs(
+    bot: &mut GeneDBot,
+    genedb_id: &String,
+    ortholog_qs: &Vec<String>,
+    q: &String,
+) {
+    if ortholog_qs.is_empty() || !bot.is_item(q) {
+        return;
+    }
+    match bot.ec.load_entities(&bot.api.read().unwrap(), ortholog_qs) {
+        Ok(_) => {}
+        _ => return,
+    }
+    let species_q = bot.species_q();
+    for ortholog_q in ortholog_qs {
+        let ortholog_i = match bot.ec.get_entity(ortholog_q.as_str()) {
+            Some(i) => i.clone(),
+            None => continue,
+        };
+        if ortholog_i.has_target_entity("P684", q) {
+            continue;
+        }
+        if bot.orthologs.report_one_directional {
+            bot.report(
+                "orthologs",
+                genedb_id,
+                &format!("{} has ortholog {}, but not vice versa", q, ortholog_q),
+            );
+        }
+        if bot.orthologs.symmetric {
+            let reference = Reference::new(vec![
+                Snak::new_item("P3452", q), // Inferred from
+                bot.new_time_today(),
+            ]);
+            link_items_with_references(
+                bot,
+                "P684",
+                &ortholog_i,
+                q.to_string(),
+                vec![Snak::new_item("P703", &species_q)],
+                vec![reference],
+            );
+        }
+    }
+}
+
 fn link_protein_to_gene(bot: &mut GeneDBot, protein_q: &String, gene_q: &String) {
     if !bot.is_item(gene_q) || !bot.is_item(protein_q) {
         return;
@@ -245,16 +431,54 @@ fn link_protein_to_gene(bot: &mut GeneDBot, protein_q: &String, gene_q: &String)
     link_items(bot, "P702", &protein_i, gene_q.to_string());
 }
 
+/// Transcript items of new genes are created before the gene item
+fn link_transcript_to_gene(bot: &mut GeneDBot, transcript_q: &String, gene_q: &String) {
+    if !bot.is_item(gene_q) || !bot.is_item(transcript_q) {
+        return;
+    }
+    let transcript_i = match bot
+        .ec
+        .load_entity(&bot.api.read().unwrap(), transcript_q.to_string())
+    {
+        Ok(i) => i.clone(),
+        _ => return,
+    };
+    link_items(bot, "P361", &transcript_i, gene_q.to_string());
+}
+
 fn link_items(bot: &mut GeneDBot, property: &str, item: &Entity, target_q: String) {
+    link_items_with_qualifiers(bot, property, item, target_q, vec![]);
+}
+
+fn link_items_with_qualifiers(
+    bot: &mut GeneDBot,
+    property: &str,
+    item: &Entity,
+    target_q: String,
+    qualifiers: Vec<Snak>,
+) {
+    let references = bot.references();
+    link_items_with_references(bot, property, item, target_q, qualifiers, references);
+}
+
+fn link_items_with_references(
+    bot: &mut GeneDBot,
+    property: &str,
+    item: &Entity,
+    target_q: String,
+    qualifiers: Vec<Snak>,
+    references: Vec<Reference>,
+) {
     if item.has_target_entity(property, &target_q) {
         return;
     }
     let mut new_item = item.clone();
     new_item.add_claim(Statement::new_normal(
         Snak::new_item(property, &target_q),
-        vec![],
-        bot.references(),
+        qualifiers,
+        references,
     ));
+    bot.apply_retrieved_policy(&item, &mut new_item);
     let params = EntityDiffParams::all();
     let mut diff = EntityDiff::new(&item, &new_item, &params);
     diff.set_edit_summary(bot.get_edit_summary());
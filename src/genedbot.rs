@@ -7,10 +7,14 @@ extern crate regex;
 extern crate reqwest;
 extern crate serde_json;
 
+use crate::assembly::AssemblyInfo;
 use crate::literature::Papers;
 use crate::orthologs::Orthologs;
+use crate::protection::{ItemKind, ProtectionContext, ProtectionRule};
+use crate::retrieved::RetrievedPolicy;
+use crate::sequence_region::{SequenceRegion, SequenceTypeRule};
 use crate::*;
-use chrono::Local;
+use chrono::{Local, NaiveDate};
 use percent_encoding::percent_decode;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
@@ -24,7 +28,24 @@ use wikibase::entity_diff::*;
 use wikibase::*;
 
 pub const SPECIES_CONFIG_FILE: &str = "https://www.genedb.org/data/datasets.json";
+pub const GENEDB_GENE_URL: &str = "https://www.genedb.org/gene/";
+/// Classes of the items with a GeneDB ID (P3382): gene (including its subclasses) and protein
+pub const GENEDB_ID_ITEM_CLASSES: &[&str] = &["Q7187", "Q8054"];
 pub const TMHMM_Q: &str = "Q61895944";
+pub const GO_RELATION_QUALIFIER: &str = "P5102"; // nature of statement
+pub const HOST_QUALIFIER: &str = "P2975"; // host
+pub const GAF_DB: &str = "GeneDB";
+pub const GAF_OBJECT_TYPES: &[&str] = &[
+    "protein",
+    "polypeptide",
+    "gene_product",
+    "transcript",
+    "mRNA",
+    "ncRNA",
+    "tRNA",
+    "rRNA",
+    "gene",
+];
 
 #[derive(Debug, Clone)]
 pub struct GeneDBotError {}
@@ -89,6 +110,23 @@ pub trait Toolbox {
         )
     }
 
+    fn new_quantity_with_unit(&self, property: &str, amount: f64, unit_q: &str) -> Snak {
+        Snak::new(
+            SnakDataType::Quantity,
+            property,
+            SnakType::Value,
+            Some(DataValue::new(
+                DataValueType::Quantity,
+                Value::Quantity(QuantityValue::new(
+                    amount,
+                    None,
+                    &format!("http://www.wikidata.org/entity/{}", unit_q),
+                    None,
+                )),
+            )),
+        )
+    }
+
     fn get_edit_summary(&self) -> Option<String> {
         Some("Syncing to GeneDB (V3)".to_string())
     }
@@ -105,6 +143,25 @@ pub trait Toolbox {
     }
 }
 
+/// How protein GO annotations are summarised on gene items
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoRollup {
+    None,
+    Full,
+    Slim,
+}
+
+impl GoRollup {
+    pub fn new_from_str(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(GoRollup::None),
+            "full" => Some(GoRollup::Full),
+            "slim" => Some(GoRollup::Slim),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GeneDBot {
     pub simulate: bool,
@@ -117,21 +174,54 @@ pub struct GeneDBot {
     pub genomic_assembly_q: String,
     pub ec: EntityContainer,
     pub api: Arc<RwLock<wikibase::mediawiki::api::Api>>,
-    pub chr2q: HashMap<String, String>,
+    pub seq2q: HashMap<String, HashMap<String, String>>,
     pub genedb2q: HashMap<String, String>,
     pub protein_genedb2q: HashMap<String, String>,
+    pub transcript_genedb2q: HashMap<String, String>,
     pub evidence: evidence::Evidence,
     pub alternate_gene_subclasses: HashMap<String, String>,
     pub other_types: HashMap<String, HashMap<String, Option<bio::io::gff::Record>>>,
     pub orthologs: Orthologs,
     pub parent2child: HashMap<String, Vec<(String, String)>>,
     pub xref2prop: HashMap<String, String>,
+    pub assigned_by2q: HashMap<String, String>,
+    pub extension_relation2prop: HashMap<String, String>,
+    pub extension_db2prop: HashMap<String, (String, bool)>,
     pub aspects: HashMap<String, String>,
     pub go_term2q: HashMap<String, String>,
+    pub go_relations: HashMap<String, String>,
+    pub external_id2q: HashMap<(String, String), Option<String>>,
+    pub gaf_version: String,
     pub genes2load: Vec<String>,
     pub specific_genes_only: Option<Vec<String>>,
     pub papers: Papers,
     pub allow_empty_gaf: bool,
+    pub gpad_file: Option<String>,
+    pub gpi_file: Option<String>,
+    pub go_ontology_file: Option<String>,
+    pub go_ontology: go_ontology::GoOntology,
+    pub gaf_date_as_retrieved: bool,
+    pub go_rollup: GoRollup,
+    pub go_slim_file: Option<String>,
+    pub go_slim: HashSet<String>,
+    pub protection_rules: Vec<ProtectionRule>,
+    pub retrieved_policy: RetrievedPolicy,
+    pub description_templates: HashMap<String, HashMap<String, String>>,
+    pub extra_languages: Vec<String>,
+    pub gene_structure: bool,
+    pub transcript_items: bool,
+    pub protein_fasta_file: Option<String>,
+    pub protein_sequences: HashMap<String, proteome::ProteinSequence>,
+    pub sequence_regions: HashMap<String, SequenceRegion>,
+    pub chromosomes_updated: HashSet<String>,
+    pub evidence_reported: HashSet<String>,
+    pub sequence_type_rules: Vec<SequenceTypeRule>,
+    pub assembly: AssemblyInfo,
+    /// "SPECIES_KEY.version" or "SPECIES_KEY.accession" (lowercase) => genome assembly version/accession
+    pub assembly_overrides: HashMap<String, String>,
+    /// SPECIES_KEY (lowercase) => additional GAF taxon IDs for the species, eg the species taxon for a strain
+    pub gaf_taxa: HashMap<String, Vec<String>>,
+    pub gaf_skipped_taxa: HashSet<String>,
 }
 
 impl Toolbox for GeneDBot {}
@@ -148,6 +238,60 @@ impl GeneDBot {
             simulate: false,
             verbose: false,
             allow_empty_gaf: false,
+            gpad_file: None,
+            gpi_file: None,
+            go_ontology_file: None,
+            go_ontology: go_ontology::GoOntology::new(),
+            gaf_date_as_retrieved: false,
+            go_rollup: GoRollup::None,
+            go_slim_file: None,
+            go_slim: HashSet::new(),
+            protection_rules: ProtectionRule::new_list_from_str(
+                protection::DEFAULT_PROTECTION_RULES,
+            )
+            .0,
+            retrieved_policy: RetrievedPolicy::OnChange,
+            // Language => item type (optionally prefixed with "SPECIES_CODE.") => description template
+            description_templates: vec![(
+                "en".to_string(),
+                vec![
+                    ("gene", "gene {genedb_id} in {species}"),
+                    (
+                        "protein_coding_gene",
+                        "protein-coding gene {genedb_id} in {species}",
+                    ),
+                    ("pseudogene", "pseudogene {genedb_id} in {species}"),
+                    ("trna_gene", "tRNA gene {genedb_id} in {species}"),
+                    ("rrna_gene", "rRNA gene {genedb_id} in {species}"),
+                    ("snorna_gene", "snoRNA gene {genedb_id} in {species}"),
+                    ("ncrna_gene", "ncRNA gene {genedb_id} in {species}"),
+                    ("snrna_gene", "snRNA gene {genedb_id} in {species}"),
+                    ("protein", "protein {genedb_id} found in {species}"),
+                    ("mrna", "mRNA in {species}"),
+                    (
+                        "pseudogenic_transcript",
+                        "pseudogenic transcript in {species}",
+                    ),
+                ]
+                .iter()
+                .map(|x| (x.0.to_string(), x.1.to_string()))
+                .collect(),
+            )]
+            .into_iter()
+            .collect(),
+            extra_languages: vec![],
+            gene_structure: false,
+            transcript_items: false,
+            protein_fasta_file: None,
+            protein_sequences: HashMap::new(),
+            sequence_regions: HashMap::new(),
+            chromosomes_updated: HashSet::new(),
+            evidence_reported: HashSet::new(),
+            sequence_type_rules: vec![],
+            assembly: AssemblyInfo::new(),
+            assembly_overrides: HashMap::new(),
+            gaf_taxa: HashMap::new(),
+            gaf_skipped_taxa: HashSet::new(),
             product_term_becomes_label: true,
             gff: HashMap::new(),
             gaf: HashMap::new(),
@@ -156,14 +300,17 @@ impl GeneDBot {
             genomic_assembly_q: "".to_string(),
             ec: EntityContainer::new(),
             api: api2,
-            chr2q: HashMap::new(),
+            seq2q: HashMap::new(),
             genedb2q: HashMap::new(),
             protein_genedb2q: HashMap::new(),
+            transcript_genedb2q: HashMap::new(),
             evidence: evidence::Evidence::new(),
             other_types: HashMap::new(),
             orthologs: Orthologs::new(),
             parent2child: HashMap::new(),
             go_term2q: HashMap::new(),
+            external_id2q: HashMap::new(),
+            gaf_version: "".to_string(),
             specific_genes_only: None,
             papers: Papers::new(&api),
             genes2load: vec![],
@@ -171,10 +318,59 @@ impl GeneDBot {
                 .iter()
                 .map(|x| (x.0.to_string(), x.1.to_string()))
                 .collect(),
+            go_relations: vec![
+                ("enables", "RO:0002327"),
+                ("contributes_to", "RO:0002326"),
+                ("involved_in", "RO:0002331"),
+                ("acts_upstream_of", "RO:0002263"),
+                ("acts_upstream_of_or_within", "RO:0002264"),
+                ("acts_upstream_of_positive_effect", "RO:0004034"),
+                ("acts_upstream_of_negative_effect", "RO:0004035"),
+                ("acts_upstream_of_or_within_positive_effect", "RO:0004032"),
+                ("acts_upstream_of_or_within_negative_effect", "RO:0004033"),
+                ("located_in", "RO:0001025"),
+                ("part_of", "BFO:0000050"),
+                ("is_active_in", "RO:0002432"),
+                ("colocalizes_with", "RO:0002325"),
+            ]
+            .iter()
+            .map(|x| (x.0.to_string(), x.1.to_string()))
+            .collect(),
             xref2prop: vec![("UniProtKB", "P352")]
                 .iter()
                 .map(|x| (x.0.to_string(), x.1.to_string()))
                 .collect(),
+            // GAF assigned_by => curator item
+            assigned_by2q: vec![
+                ("GeneDB", "Q5531047"),
+                ("UniProt", "Q905695"),
+                ("UniProtKB", "Q905695"),
+                ("InterPro", "Q3047275"),
+                ("GOC", "Q135085"),
+                ("GO_Central", "Q135085"),
+                ("Reactome", "Q2134522"),
+            ]
+            .iter()
+            .map(|x| (x.0.to_string(), x.1.to_string()))
+            .collect(),
+            extension_relation2prop: vec![
+                ("occurs_in", "P276"),  // Location
+                ("part_of", "P361"),    // Part of
+                ("has_input", "P2283"), // Uses
+            ]
+            .iter()
+            .map(|x| (x.0.to_string(), x.1.to_string()))
+            .collect(),
+            // Database prefix => (property, value includes prefix)
+            extension_db2prop: vec![
+                ("CL", "P7963", true),
+                ("UBERON", "P1554", false),
+                ("CHEBI", "P683", false),
+                ("NCBITaxon", "P685", false),
+            ]
+            .iter()
+            .map(|x| (x.0.to_string(), (x.1.to_string(), x.2)))
+            .collect(),
             alternate_gene_subclasses: vec![
                 ("tRNA", "Q201448"),
                 ("rRNA", "Q215980"),
@@ -217,6 +413,30 @@ impl GeneDBot {
         self.config.wikidata_id.to_owned()
     }
 
+    /// Returns the assembly version or accession ("version" or "accession") set for the current species
+    pub fn get_assembly_override(&self, key: &str) -> Option<String> {
+        let key = format!("{}.{}", self.species_key.to_lowercase(), key);
+        self.assembly_overrides.get(&key).cloned()
+    }
+
+    /// Returns the GAF taxa ("taxon:ID") accepted for the species being run; empty if there is no taxon ID
+    pub fn accepted_gaf_taxa(&self) -> Vec<String> {
+        if self.config.taxon_id.is_empty() {
+            return vec![];
+        }
+        let mut ret = vec![format!("taxon:{}", &self.config.taxon_id)];
+        match self.gaf_taxa.get(&self.species_key.to_lowercase()) {
+            Some(taxa) => ret.extend(taxa.iter().map(|t| format!("taxon:{}", t))),
+            None => {}
+        }
+        ret
+    }
+
+    /// Replaces "{species}" in a file name or URL with the species key, for per-species input files
+    pub fn species_location(&self, location: &str) -> String {
+        location.replace("{species}", &self.species_key)
+    }
+
     fn get_log_filename(&self) -> String {
         "logs/".to_string() + &self.species_key + ".log"
     }
@@ -235,6 +455,42 @@ impl GeneDBot {
         }
     }
 
+    fn get_report_filename(&self, report: &str) -> String {
+        format!("logs/{}.{}.report", &self.species_key, report)
+    }
+
+    /// Writes a message into a named report file, for human review
+    pub fn report(&self, report: &str, genedb_id: &String, message: &str) {
+        let logfile_result = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(true)
+            .open(self.get_report_filename(report));
+        match logfile_result {
+            Ok(mut logfile) => logfile
+                .write_fmt(format_args!("{}\t{}\n", genedb_id, message))
+                .unwrap(),
+            _ => println!("{} REPORT {}: {}", report, genedb_id, message),
+        }
+    }
+
+    fn remove_reports(&self) {
+        let prefix = format!("{}.", &self.species_key);
+        match fs::read_dir("logs") {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| match path.file_name().and_then(|n| n.to_str()) {
+                    Some(name) => name.starts_with(&prefix) && name.ends_with(".report"),
+                    None => false,
+                })
+                .for_each(|path| match fs::remove_file(path) {
+                    _ => {}
+                }),
+            _ => {}
+        }
+    }
+
     pub fn load_config_file(&mut self, species_key: &str) -> Result<(), reqwest::Error> {
         self.set_species(species_key);
         let config: serde_json::Value = reqwest::blocking::get(SPECIES_CONFIG_FILE)?.json()?;
@@ -263,10 +519,49 @@ impl GeneDBot {
     }
 
     pub fn references(&self) -> Vec<Reference> {
-        vec![Reference::new(vec![
-            Snak::new_item("P248", "Q5531047"),
-            self.new_time_today(),
-        ])]
+        vec![Reference::new(self.genedb_reference_snaks(None))]
+    }
+
+    /// Reference "stated in GeneDB", with the release version (if known), and the gene page URL (if a gene is given)
+    pub fn genedb_reference(&self, gene_genedb_id: &String) -> Reference {
+        Reference::new(self.genedb_reference_snaks(Some(gene_genedb_id)))
+    }
+
+    fn genedb_reference_snaks(&self, gene_genedb_id: Option<&String>) -> Vec<Snak> {
+        let mut ret = vec![Snak::new_item("P248", "Q5531047")];
+        match self.release_version_snak() {
+            Some(snak) => ret.push(snak),
+            None => {}
+        }
+        match gene_genedb_id {
+            Some(id) => ret.push(Snak::new_string(
+                "P854",
+                &format!("{}{}", GENEDB_GENE_URL, id),
+            )),
+            None => {}
+        }
+        ret.push(self.new_time_today());
+        ret
+    }
+
+    /// Reference "curator: GeneDB", with the release version (if known)
+    pub fn genedb_curator_reference(&self) -> Reference {
+        let mut snaks = vec![Snak::new_item("P1640", "Q5531047")];
+        match self.release_version_snak() {
+            Some(snak) => snaks.push(snak),
+            None => {}
+        }
+        snaks.push(self.new_time_today());
+        Reference::new(snaks)
+    }
+
+    /// The GeneDB release version from datasets.json, as a reference snak
+    pub fn release_version_snak(&self) -> Option<Snak> {
+        if self.config.version.is_empty() {
+            None
+        } else {
+            Some(Snak::new_string("P348", &self.config.version))
+        }
     }
 
     pub fn parent_taxon_q(&self) -> Option<String> {
@@ -298,37 +593,166 @@ impl GeneDBot {
         }
     }
 
+    pub fn get_sequence_type(&self, id: &str) -> String {
+        sequence_region::infer_sequence_type(
+            id,
+            self.sequence_regions.get(id),
+            &self.sequence_type_rules,
+        )
+    }
+
+    /// The class item for a sequence type; chromosome items have always used Q37748
+    pub fn get_sequence_class_q(&mut self, sequence_type: &str) -> Option<String> {
+        if sequence_type == sequence_region::DEFAULT_SEQUENCE_TYPE {
+            return Some(sequence_region::CHROMOSOME_Q.to_string());
+        }
+        let so_id = sequence_region::get_so_id(sequence_type)?;
+        self.get_item_for_external_id("P3986", &so_id)
+    }
+
+    /// Item for the sequence; items of another sequence type are used (and their class fixed) if there is none of the right type
+    fn get_sequence_entity_id(&self, id: &str, sequence_type: &str) -> Option<String> {
+        match self.seq2q.get(sequence_type).and_then(|m| m.get(id)) {
+            Some(q) => return Some(q.to_owned()),
+            None => {}
+        }
+        self.seq2q
+            .values()
+            .filter_map(|m| m.get(id))
+            .next()
+            .cloned()
+    }
+
+    /// Gets or creates the item for a sequence (chromosome, contig, scaffold...)
     pub fn get_or_create_chromosome_entity(&mut self, id: &str) -> Option<String> {
-        match self.chr2q.get(id) {
-            Some(q) => return Some(q.clone()),
+        let sequence_type = self.get_sequence_type(id);
+        let class_q = match self.get_sequence_class_q(&sequence_type) {
+            Some(q) => q,
+            None => {
+                self.log(
+                    &id.to_string(),
+                    &format!("No Wikidata item for sequence type {}", &sequence_type),
+                );
+                return None;
+            }
+        };
+        match self.get_sequence_entity_id(id, &sequence_type) {
+            Some(q) => {
+                if !self.chromosomes_updated.contains(id) {
+                    self.chromosomes_updated.insert(id.to_string());
+                    self.update_chromosome_entity(id, &class_q, &q);
+                }
+                return Some(q);
+            }
             None => {}
         }
 
         let mut new_item = Entity::new_empty_item();
         new_item.set_label(LocaleString::new("en", id));
-        new_item.add_claim(Statement::new_normal(
-            Snak::new_item("P31", "Q37748"),
-            vec![],
-            self.references(),
-        ));
-        new_item.add_claim(Statement::new_normal(
-            Snak::new_item("P703", self.species_q().as_str()),
-            vec![],
-            self.references(),
-        ));
+        self.add_chromosome_statements(id, &class_q, &mut new_item);
 
         let params = EntityDiffParams::all();
-        let mut diff = EntityDiff::new(&Entity::new_empty_item(), &new_item, &params);
+        let original_item = Entity::new_empty_item();
+        self.apply_retrieved_policy(&original_item, &mut new_item);
+        let mut diff = EntityDiff::new(&original_item, &new_item, &params);
         diff.set_edit_summary(self.get_edit_summary());
+        self.protect_claims(ItemKind::Chromosome, &mut diff, &original_item);
         match self.ec.apply_diff(&mut self.api.write().unwrap(), &diff) {
             Some(q) => {
-                self.chr2q.insert(id.to_string(), q.clone());
+                self.seq2q
+                    .entry(sequence_type)
+                    .or_insert(HashMap::new())
+                    .insert(id.to_string(), q.clone());
                 Some(q)
             }
             None => None,
         }
     }
 
+    /// Adds class, taxon, and (if known from the GFF) length, accessions and the genomic assembly
+    fn add_chromosome_statements(&mut self, id: &str, class_q: &str, item: &mut Entity) {
+        let mut snaks = vec![
+            Snak::new_item("P31", class_q),
+            Snak::new_item("P703", self.species_q().as_str()),
+        ];
+        match self.sequence_regions.get(id).cloned() {
+            Some(region) => {
+                match self.get_item_for_external_id("P3986", structure::SO_BASE_PAIR) {
+                    Some(base_pair_q) => snaks.push(self.new_quantity_with_unit(
+                        "P2043",
+                        region.length() as f64,
+                        &base_pair_q,
+                    )),
+                    None => self.log(&id.to_string(), "No Wikidata item for base pair"),
+                }
+                snaks.append(&mut region.accession_snaks());
+            }
+            None => {}
+        }
+        if self.is_item(&self.genomic_assembly_q) {
+            snaks.push(Snak::new_item("P361", &self.genomic_assembly_q)); // Part of
+        }
+        for snak in snaks {
+            item.add_claim(Statement::new_normal(snak, vec![], self.references()));
+        }
+    }
+
+    /// Updates an existing chromosome item with the statements from the current GFF
+    fn update_chromosome_entity(&mut self, id: &str, class_q: &str, q: &String) {
+        let original_item = match self
+            .ec
+            .load_entity(&self.api.read().unwrap(), q.to_string())
+        {
+            Ok(i) => i.clone(),
+            Err(e) => {
+                self.log(
+                    &id.to_string(),
+                    &format!("Could not load sequence item {}: {}", q, e),
+                );
+                return;
+            }
+        };
+        let mut new_item = Entity::new_empty_item();
+        self.add_chromosome_statements(id, class_q, &mut new_item);
+        let my_props = vec![
+            "P31",   // Instance of (sequence type)
+            "P2043", // Length
+            "P2249", // RefSeq genome ID
+            "P973",  // Described at URL (INSDC)
+            "P361",  // Part of (genomic assembly)
+        ];
+
+        let mut params = EntityDiffParams::none();
+        params.claims.add = EntityDiffParamState::All;
+        params.claims.alter = EntityDiffParamState::All;
+        params.claims.remove = EntityDiffParamState::some(&my_props);
+        params.references.list.push((
+            EntityDiffParamState::some(&my_props),
+            self.retrieved_policy.reference_diff_state(),
+        ));
+        self.apply_retrieved_policy(&original_item, &mut new_item);
+        let mut diff = EntityDiff::new(&original_item, &new_item, &params);
+        diff.set_edit_summary(self.get_edit_summary());
+        self.protect_claims(ItemKind::Chromosome, &mut diff, &original_item);
+        if diff.is_empty() {
+            return;
+        }
+        if self.verbose {
+            println!(
+                "\nCHROMOSOME {}/{}:\n{}",
+                id,
+                q,
+                serde_json::to_string_pretty(&diff.actions()).unwrap()
+            );
+        }
+        if !self.simulate {
+            match self.ec.apply_diff(&mut self.api.write().unwrap(), &diff) {
+                Some(_) => {}
+                None => self.log(&id.to_string(), "Applying chromosome diff returned nothing"),
+            }
+        }
+    }
+
     pub fn process_product_controlled_curation(
         &mut self,
         gff: &bio::io::gff::Record,
@@ -388,30 +812,21 @@ impl GeneDBot {
                     }
 
                     // Valid term confirmed, new statement primed
-                    let mut reference = Reference::new(vec![
-                        Snak::new_item("P1640", "Q5531047"),
-                        self.new_time_today(),
-                    ]);
+                    let mut reference = self.genedb_curator_reference();
 
                     // Add evidence code
                     match kv.get("evidence") {
-                        Some(evidence_text) => {
-                            match self
-                                .evidence
-                                .label2q
-                                .get(&self.evidence.normalize_label(evidence_text))
-                            {
-                                Some(ecq) => {
-                                    statement.add_qualifier_snak(Snak::new_item("P459", ecq));
-                                }
-                                None => {
-                                    self.log(
-                                        &genedb_id,
-                                        &format!("Unrecognized evidence: '{}'", &evidence_text),
-                                    );
-                                }
+                        Some(evidence_text) => match self.get_evidence_item(evidence_text) {
+                            Some(ecq) => {
+                                statement.add_qualifier_snak(Snak::new_item("P459", &ecq));
                             }
-                        }
+                            None => {
+                                self.log(
+                                    &genedb_id,
+                                    &format!("Unrecognized evidence: '{}'", &evidence_text),
+                                );
+                            }
+                        },
                         None => {}
                     }
 
@@ -567,10 +982,7 @@ impl GeneDBot {
             return;
         } // Why is this?
 
-        let reference = Reference::new(vec![
-            Snak::new_item("P1640", "Q5531047"),
-            self.new_time_today(),
-        ]);
+        let reference = self.genedb_curator_reference();
         let mut qualifiers = vec![];
 
         match apk.get("rank") {
@@ -583,12 +995,8 @@ impl GeneDBot {
         }
 
         match apk.get("evidence") {
-            Some(evidence) => match self
-                .evidence
-                .label2q
-                .get(&self.evidence.normalize_label(evidence))
-            {
-                Some(ecq) => qualifiers.push(Snak::new_item("P459", ecq)),
+            Some(evidence) => match self.get_evidence_item(evidence) {
+                Some(ecq) => qualifiers.push(Snak::new_item("P459", &ecq)),
                 None => {}
             },
             None => {}
@@ -692,7 +1100,7 @@ impl GeneDBot {
             None => {}
         }
         let sparql = format!("SELECT ?q {{ ?q wdt:P686 '{}' }}", &go_term);
-        let sparql_result = self.api.read().unwrap().sparql_query(&sparql).ok()?;
+        let sparql_result = self.api.read().unwrap().sparql_query(sparql).ok()?;
         for b in sparql_result["results"]["bindings"].as_array()? {
             let q = match b["q"]["value"].as_str() {
                 Some(s) => self.api.read().unwrap().extract_entity_from_uri(s).ok()?,
@@ -704,6 +1112,396 @@ impl GeneDBot {
         None
     }
 
+    /// Returns the Wikidata time value for a GAF date (YYYYMMDD); malformed and future dates are logged
+    pub fn get_time_for_gaf_date(&self, genedb_id: &String, date: &str) -> Option<String> {
+        let today = Local::now().naive_local().date();
+        match gaf_date_to_time(date, today) {
+            Ok(time) => Some(time),
+            Err(e) => {
+                self.log(genedb_id, &e);
+                None
+            }
+        }
+    }
+
+    /// Checks that a GAF row belongs to the species being run (DB and first taxon columns).
+    /// The first taxon may be the species taxon ID, or one of the equivalent taxa in `[gaf_taxa]`.
+    pub fn check_gaf_record(&self, record: &bio::io::gaf::Record) -> Result<(), String> {
+        if !record.db().is_empty() && record.db() != GAF_DB {
+            return Err(format!("GAF row from DB '{}', not {}", record.db(), GAF_DB));
+        }
+        let object_type = record.db_object_type();
+        // GPI 2.0 uses SO/PR IDs as object types
+        if !object_type.is_empty()
+            && !object_type.contains(':')
+            && !GAF_OBJECT_TYPES.contains(&object_type)
+        {
+            return Err(format!("GAF row for object type '{}'", object_type));
+        }
+        let accepted_taxa = self.accepted_gaf_taxa();
+        if accepted_taxa.is_empty() {
+            return Ok(());
+        }
+        match record.taxon().get(0) {
+            Some(taxon) => {
+                if !accepted_taxa.contains(taxon) {
+                    return Err(format!(
+                        "GAF row for taxon '{}', not {}",
+                        taxon,
+                        accepted_taxa.join("/")
+                    ));
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the host qualifiers for a host-pathogen GAF row (taxon:X|taxon:Y, Y being the host)
+    pub fn get_host_qualifiers(
+        &mut self,
+        genedb_id: &String,
+        record: &bio::io::gaf::Record,
+    ) -> Vec<Snak> {
+        let mut ret = vec![];
+        for taxon in record.taxon().iter().skip(1) {
+            let taxon_id = taxon.trim_start_matches("taxon:");
+            match self.get_item_for_external_id("P685", taxon_id) {
+                Some(q) => ret.push(Snak::new_item(HOST_QUALIFIER, &q)),
+                None => self.log(
+                    genedb_id,
+                    &format!("No Wikidata item for host taxon '{}'", taxon),
+                ),
+            }
+        }
+        ret
+    }
+
+    /// Species name for descriptions, eg "Plasmodium falciparum 3D7"
+    pub fn species_name(&self) -> String {
+        let mut ret = format!("{} {}", &self.config.genus, &self.config.species);
+        if !self.config.strain.is_empty() && !ret.contains(&self.config.strain) {
+            ret = format!("{} {}", &ret, &self.config.strain);
+        }
+        ret.trim().to_string()
+    }
+
+    /// Languages for labels, aliases and descriptions; English first
+    pub fn languages(&self) -> Vec<String> {
+        let mut ret = vec!["en".to_string()];
+        for language in &self.extra_languages {
+            if !ret.contains(language) {
+                ret.push(language.to_string());
+            }
+        }
+        ret
+    }
+
+    /// Returns the description for an item type ("gene", "protein" etc.) in a language, from a species-specific or general template
+    pub fn get_description(
+        &self,
+        language: &str,
+        item_type: &str,
+        genedb_id: &str,
+    ) -> Option<String> {
+        let templates = self.description_templates.get(language)?;
+        let item_type = item_type.to_lowercase();
+        let species_specific = format!("{}.{}", self.species_key.to_lowercase(), &item_type);
+        let template = match templates.get(&species_specific) {
+            Some(template) => template,
+            None => templates.get(&item_type)?,
+        };
+        Some(self.fill_description_template(template, genedb_id))
+    }
+
+    fn fill_description_template(&self, template: &str, genedb_id: &str) -> String {
+        template
+            .replace("{species}", &self.species_name())
+            .replace("{genus}", &self.config.genus)
+            .replace("{strain}", &self.config.strain)
+            .replace("{common_name}", &self.config.common_name)
+            .replace("{genedb_id}", genedb_id)
+            .trim()
+            .to_string()
+    }
+
+    /// Sets the generated descriptions on the new item, in all languages. Existing descriptions that the bot
+    /// did not generate (eg written by a human), or in a language without template, are kept.
+    pub fn set_description(
+        &self,
+        original_item: &Entity,
+        item: &mut Entity,
+        item_type: &str,
+        genedb_id: &str,
+    ) {
+        for language in self.languages() {
+            let existing = original_item.description_in_locale(&language);
+            let description = match self.get_description(&language, item_type, genedb_id) {
+                Some(d) => d,
+                None => {
+                    match existing {
+                        Some(existing) => {
+                            item.set_description(LocaleString::new(&language, existing))
+                        }
+                        None => {}
+                    }
+                    continue;
+                }
+            };
+            match existing {
+                Some(existing) => {
+                    let generated: Vec<String> = self
+                        .description_templates
+                        .get(&language)
+                        .map(|templates| templates.keys().cloned().collect())
+                        .unwrap_or(vec![])
+                        .iter()
+                        .filter(|key| !key.contains('.'))
+                        .filter_map(|key| self.get_description(&language, key, genedb_id))
+                        .collect();
+                    if existing != description && !generated.iter().any(|d| d == existing) {
+                        item.set_description(LocaleString::new(&language, existing));
+                        continue;
+                    }
+                }
+                None => {}
+            }
+            item.set_description(LocaleString::new(&language, &description));
+        }
+    }
+
+    /// Keeps existing labels and aliases in the additional languages; items without a label get the fallback label
+    pub fn set_extra_language_labels(
+        &self,
+        original_item: &Entity,
+        item: &mut Entity,
+        fallback_label: &str,
+    ) {
+        for language in &self.extra_languages {
+            match original_item.label_in_locale(language) {
+                Some(label) => item.set_label(LocaleString::new(language, label)),
+                None => item.set_label(LocaleString::new(language, fallback_label)),
+            }
+            original_item
+                .aliases()
+                .iter()
+                .filter(|alias| alias.language() == language.as_str())
+                .for_each(|alias| item.add_alias(alias.clone()));
+        }
+    }
+
+    /// Sets the P813 (retrieved) dates of the new item according to the retrieved policy, before diffing
+    pub fn apply_retrieved_policy(&self, original_item: &Entity, item: &mut Entity) {
+        let today = Local::now().naive_local().date();
+        self.retrieved_policy.apply(original_item, item, today);
+    }
+
+    /// Applies the protection rules to a diff, so third-party statements are not removed; protected statements are reported
+    pub fn protect_claims(&self, kind: ItemKind, diff: &mut EntityDiff, original_item: &Entity) {
+        let context = ProtectionContext {
+            own_curators: self.get_curator_items(),
+            last_editor_is_human: protection::needs_editor(&self.protection_rules, &kind)
+                && self.is_last_editor_human(original_item.id()),
+        };
+        let protected =
+            protection::apply_rules(&self.protection_rules, &kind, diff, original_item, &context);
+        for claim_id in protected {
+            self.report(
+                "protected",
+                &original_item.id().to_string(),
+                &format!("Not removing protected statement {}", &claim_id),
+            );
+        }
+    }
+
+    /// Checks if the last edit of an item was made by a human, rather than a bot account.
+    /// Errs on the side of caution if this can not be determined.
+    fn is_last_editor_human(&self, q: &str) -> bool {
+        if !self.is_item(&q.to_string()) {
+            return false;
+        }
+        let api = self.api.read().unwrap();
+        let params: HashMap<String, String> = vec![
+            ("action", "query"),
+            ("prop", "revisions"),
+            ("titles", q),
+            ("rvprop", "user"),
+            ("rvlimit", "1"),
+        ]
+        .into_iter()
+        .map(|(x, y)| (x.to_string(), y.to_string()))
+        .collect();
+        let res = match api.get_query_api_json(&params) {
+            Ok(res) => res,
+            Err(_) => return true,
+        };
+        let user = match res["query"]["pages"]
+            .as_object()
+            .and_then(|pages| pages.values().next())
+            .and_then(|page| page["revisions"][0]["user"].as_str())
+        {
+            Some(user) => user.to_string(),
+            None => return true,
+        };
+        let params: HashMap<String, String> = vec![
+            ("action", "query"),
+            ("list", "users"),
+            ("ususers", user.as_str()),
+            ("usprop", "groups"),
+        ]
+        .into_iter()
+        .map(|(x, y)| (x.to_string(), y.to_string()))
+        .collect();
+        match api.get_query_api_json(&params) {
+            Ok(res) => match res["query"]["users"][0]["groups"].as_array() {
+                Some(groups) => !groups.iter().any(|g| g.as_str() == Some("bot")),
+                None => true, // Anonymous
+            },
+            Err(_) => true,
+        }
+    }
+
+    /// Returns the curator item for a GAF assigned_by value. Unknown values are logged, and get no curator,
+    /// so third-party annotations are not credited to GeneDB.
+    pub fn get_curator_for_assigned_by(
+        &self,
+        genedb_id: &String,
+        assigned_by: &str,
+    ) -> Option<String> {
+        match self.assigned_by2q.get(assigned_by.trim()) {
+            Some(q) => Some(q.to_string()),
+            None => {
+                self.log(
+                    genedb_id,
+                    &format!(
+                        "Unknown GAF assigned_by '{}', reference without curator",
+                        assigned_by
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    /// Returns all curator items the bot uses in references
+    pub fn get_curator_items(&self) -> HashSet<String> {
+        let mut ret: HashSet<String> = self.assigned_by2q.values().cloned().collect();
+        ret.insert("Q5531047".to_string());
+        ret
+    }
+
+    /// Checks a GO term against the GO ontology file, if any.
+    /// Returns the term to use (possibly a replacement); obsolete terms are reported.
+    pub fn check_go_term(&self, genedb_id: &String, go_term: &String) -> Option<String> {
+        match self.go_ontology.get_status(go_term) {
+            go_ontology::GoTermStatus::Current(term) => Some(term),
+            go_ontology::GoTermStatus::Replaced(term) => {
+                self.report(
+                    "obsolete_go_terms",
+                    genedb_id,
+                    &format!("{} is obsolete or secondary, using {}", go_term, &term),
+                );
+                Some(term)
+            }
+            go_ontology::GoTermStatus::Obsolete(consider) => {
+                let message = if consider.is_empty() {
+                    format!("{} is obsolete, annotation skipped", go_term)
+                } else {
+                    format!(
+                        "{} is obsolete, annotation skipped; consider {}",
+                        go_term,
+                        consider.join(", ")
+                    )
+                };
+                self.report("obsolete_go_terms", genedb_id, &message);
+                None
+            }
+        }
+    }
+
+    /// Finds the item with the given external ID (eg P3590 for Relations Ontology IDs), cached
+    pub fn get_item_for_external_id(&mut self, property: &str, id: &str) -> Option<String> {
+        let sparql = format!(
+            "SELECT ?q {{ ?q wdt:{} '{}' }}",
+            property,
+            id.replace("'", "\\'")
+        );
+        self.get_item_for_sparql((property.to_string(), id.to_string()), &sparql)
+    }
+
+    /// Returns the gene or protein item with a GeneDB ID; other items (eg transcripts) are ignored
+    pub fn get_gene_or_protein_item_for_genedb_id(&mut self, id: &str) -> Option<String> {
+        let sparql = format!(
+            "SELECT ?q {{ ?q wdt:P3382 '{}' ; wdt:P31/wdt:P279* ?class . VALUES ?class {{ wd:{} }} }}",
+            id.replace("'", "\\'"),
+            GENEDB_ID_ITEM_CLASSES.join(" wd:")
+        );
+        self.get_item_for_sparql(
+            ("P3382:gene_or_protein".to_string(), id.to_string()),
+            &sparql,
+        )
+    }
+
+    /// Returns the first ?q of a SPARQL query, cached by key
+    fn get_item_for_sparql(&mut self, key: (String, String), sparql: &str) -> Option<String> {
+        match self.external_id2q.get(&key) {
+            Some(q) => return q.to_owned(),
+            None => {}
+        }
+        let sparql_result = self.api.read().unwrap().sparql_query(&sparql).ok()?;
+        let mut ret: Option<String> = None;
+        for b in sparql_result["results"]["bindings"].as_array()? {
+            match b["q"]["value"].as_str() {
+                Some(s) => {
+                    ret = self.api.read().unwrap().extract_entity_from_uri(s).ok();
+                    break;
+                }
+                None => continue,
+            }
+        }
+        self.external_id2q.insert(key, ret.clone());
+        ret
+    }
+
+    /// Returns the qualifier for a GO relation (GAF qualifier column, eg "enables")
+    pub fn get_go_relation_qualifier(&mut self, relation: &str) -> Option<Snak> {
+        let ro_id = self.go_relations.get(relation)?.to_owned();
+        let q = self.get_item_for_external_id("P3590", &ro_id)?;
+        Some(Snak::new_item(GO_RELATION_QUALIFIER, &q))
+    }
+
+    /// Returns the evidence item for a GO evidence code, a GFF evidence label, or an ECO ID.
+    /// Items found only by their label are reported once per evidence code, as labels on Wikidata can change.
+    pub fn get_evidence_item(&mut self, evidence: &str) -> Option<String> {
+        match self.evidence.get_item(evidence)? {
+            evidence::EvidenceMatch::Eco(q) => Some(q),
+            evidence::EvidenceMatch::Label(q) => {
+                if self.evidence_reported.insert(evidence.to_string()) {
+                    self.report(
+                        "evidence",
+                        &evidence.to_string(),
+                        &format!("matched {} by label only, not by ECO ID", &q),
+                    );
+                }
+                Some(q)
+            }
+        }
+    }
+
+    /// Checks the GAF version (from the file header) against a minimum version
+    pub fn gaf_version_at_least(&self, major: u32, minor: u32) -> bool {
+        let parts: Vec<u32> = self
+            .gaf_version
+            .split('.')
+            .filter_map(|s| s.parse::<u32>().ok())
+            .collect();
+        match (parts.get(0), parts.get(1)) {
+            (Some(a), Some(b)) => (*a, *b) >= (major, minor),
+            (Some(a), None) => *a > major || (*a == major && minor == 0),
+            _ => false,
+        }
+    }
+
     fn get_gene_ids_to_process(&self) -> Vec<String> {
         match &self.specific_genes_only {
             Some(genes) => genes.clone(),
@@ -726,14 +1524,29 @@ impl GeneDBot {
     }
 
     pub fn init(&mut self) -> Result<(), Box<dyn Error>> {
-        loader::init(self)?;
+        // Logs are cleared before loading, as rows skipped by the loader are logged
         match fs::create_dir_all("logs") {
             _ => match fs::remove_file(self.get_log_filename()) {
                 _ => {}
             },
         }
-        Ok(())
+        self.remove_reports();
+        loader::init(self)
+    }
+}
+
+/// Converts a GAF date (YYYYMMDD) into a Wikidata time value, checking it is a valid date not after today
+pub fn gaf_date_to_time(date: &str, today: NaiveDate) -> Result<String, String> {
+    let date = date.trim();
+    if date.len() != 8 || !date.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Malformed GAF date '{}'", date));
+    }
+    let d = NaiveDate::parse_from_str(date, "%Y%m%d")
+        .map_err(|_| format!("Invalid GAF date '{}'", date))?;
+    if d > today {
+        return Err(format!("GAF date '{}' is in the future", date));
     }
+    Ok(format!("{}", d.format("+%Y-%m-%dT00:00:00Z")))
 }
 
 #[cfg(test)]
@@ -793,6 +1606,158 @@ mod tests {
         assert_eq!(bot.xref2prop.get("UniProtKB").unwrap(), "P352");
     }
 
+    #[test]
+    fn test_gaf_date_to_time() {
+        let today = NaiveDate::from_ymd_opt(2020, 1, 31).unwrap();
+        assert_eq!(
+            gaf_date_to_time("20190310", today),
+            Ok("+2019-03-10T00:00:00Z".to_string())
+        );
+        assert_eq!(
+            gaf_date_to_time("20200131", today),
+            Ok("+2020-01-31T00:00:00Z".to_string())
+        );
+        assert!(gaf_date_to_time("20200201", today).is_err()); // Future
+        assert!(gaf_date_to_time("20190230", today).is_err()); // No Feb 30
+        assert!(gaf_date_to_time("2019-03-10", today).is_err());
+        assert!(gaf_date_to_time("", today).is_err());
+    }
+
+    #[test]
+    fn test_check_gaf_record() {
+        let mut bot = GeneDBot::new();
+        bot.config.taxon_id = "5833".to_string();
+        let mut record = bio::io::gaf::Record::new();
+        *record.db_mut() = "GeneDB".to_string();
+        record.taxon_mut().push("taxon:5833".to_string());
+        assert!(bot.check_gaf_record(&record).is_ok());
+        record.taxon_mut().push("taxon:9606".to_string());
+        assert!(bot.check_gaf_record(&record).is_ok());
+        *record.taxon_mut() = vec!["taxon:9606".to_string()];
+        assert!(bot.check_gaf_record(&record).is_err());
+        *record.taxon_mut() = vec!["taxon:5833".to_string()];
+        *record.db_mut() = "UniProtKB".to_string();
+        assert!(bot.check_gaf_record(&record).is_err());
+        *record.db_mut() = "GeneDB".to_string();
+        *record.db_object_type_mut() = "protein".to_string();
+        assert!(bot.check_gaf_record(&record).is_ok());
+        *record.db_object_type_mut() = "pathway".to_string();
+        assert!(bot.check_gaf_record(&record).is_err());
+        *record.db_object_type_mut() = "protein".to_string();
+        *record.taxon_mut() = vec!["taxon:36329".to_string()];
+        assert!(bot.check_gaf_record(&record).is_err());
+        bot.species_key = "Pfalciparum".to_string();
+        bot.gaf_taxa
+            .insert("pfalciparum".to_string(), vec!["36329".to_string()]);
+        assert!(bot.check_gaf_record(&record).is_ok());
+    }
+
+    #[test]
+    fn test_get_description() {
+        let mut bot = GeneDBot::new();
+        bot.species_key = "Pfalciparum".to_string();
+        bot.config.genus = "Plasmodium".to_string();
+        bot.config.species = "falciparum".to_string();
+        bot.config.strain = "3D7".to_string();
+        assert_eq!(bot.species_name(), "Plasmodium falciparum 3D7");
+        assert_eq!(
+            bot.get_description("en", "protein_coding_gene", "PF3D7_0100100"),
+            Some("protein-coding gene PF3D7_0100100 in Plasmodium falciparum 3D7".to_string())
+        );
+        assert_eq!(bot.get_description("en", "foobar", "PF3D7_0100100"), None);
+        assert_eq!(bot.get_description("de", "protein", "PF3D7_0100100"), None);
+        bot.description_templates.get_mut("en").unwrap().insert(
+            "pfalciparum.protein".to_string(),
+            "malaria parasite protein".to_string(),
+        );
+        assert_eq!(
+            bot.get_description("en", "protein", "PF3D7_0100100.1"),
+            Some("malaria parasite protein".to_string())
+        );
+    }
+
+    #[test]
+    fn test_languages() {
+        let mut bot = GeneDBot::new();
+        assert_eq!(bot.languages(), vec!["en"]);
+        bot.extra_languages = vec!["de".to_string(), "en".to_string(), "fr".to_string()];
+        assert_eq!(bot.languages(), vec!["en", "de", "fr"]);
+    }
+
+    #[test]
+    fn test_set_description() {
+        let mut bot = GeneDBot::new();
+        bot.config.genus = "Plasmodium".to_string();
+        bot.config.species = "falciparum".to_string();
+        let mut original = Entity::new_empty_item();
+        let mut item = Entity::new_empty_item();
+        bot.set_description(&original, &mut item, "protein", "PF3D7_0100100.1");
+        assert_eq!(
+            item.description_in_locale("en"),
+            Some("protein PF3D7_0100100.1 found in Plasmodium falciparum")
+        );
+        // Previously generated description
+        original.set_description(LocaleString::new(
+            "en",
+            "gene PF3D7_0100100 in Plasmodium falciparum",
+        ));
+        bot.set_description(&original, &mut item, "pseudogene", "PF3D7_0100100");
+        assert_eq!(
+            item.description_in_locale("en"),
+            Some("pseudogene PF3D7_0100100 in Plasmodium falciparum")
+        );
+        // Description that does not match a configured template is kept
+        original.set_description(LocaleString::new(
+            "en",
+            "protein found in Plasmodium falciparum",
+        ));
+        bot.set_description(&original, &mut item, "protein", "PF3D7_0100100.1");
+        assert_eq!(
+            item.description_in_locale("en"),
+            Some("protein found in Plasmodium falciparum")
+        );
+        // Human-written description
+        original.set_description(LocaleString::new("en", "a very special gene"));
+        bot.set_description(&original, &mut item, "gene", "PF3D7_0100100");
+        assert_eq!(
+            item.description_in_locale("en"),
+            Some("a very special gene")
+        );
+        // Additional language, with and without template
+        bot.extra_languages = vec!["de".to_string(), "fr".to_string()];
+        bot.description_templates.insert(
+            "de".to_string(),
+            vec![("gene".to_string(), "Gen in {species}".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        original.set_description(LocaleString::new("fr", "un gène"));
+        bot.set_description(&original, &mut item, "gene", "PF3D7_0100100");
+        assert_eq!(
+            item.description_in_locale("de"),
+            Some("Gen in Plasmodium falciparum")
+        );
+        assert_eq!(item.description_in_locale("fr"), Some("un gène"));
+    }
+
+    #[test]
+    fn test_get_curator_for_assigned_by() {
+        let bot = GeneDBot::new();
+        let id = "test".to_string();
+        assert_eq!(
+            bot.get_curator_for_assigned_by(&id, "GeneDB"),
+            Some("Q5531047".to_string())
+        );
+        assert_eq!(
+            bot.get_curator_for_assigned_by(&id, "UniProt"),
+            Some("Q905695".to_string())
+        );
+        assert_eq!(bot.get_curator_for_assigned_by(&id, "FooDB"), None);
+        assert_eq!(bot.get_curator_for_assigned_by(&id, ""), None);
+        assert!(bot.get_curator_items().contains("Q3047275"));
+        assert!(bot.get_curator_items().contains("Q5531047"));
+    }
+
     #[test]
     fn test_refrences() {
         let bot = GeneDBot::new();
@@ -803,6 +1768,22 @@ mod tests {
         assert_eq!(snaks[0], Snak::new_item("P248", "Q5531047"));
     }
 
+    #[test]
+    fn test_genedb_reference() {
+        let mut bot = GeneDBot::new();
+        bot.config.version = "2019-10".to_string();
+        let reference = bot.genedb_reference(&"PF3D7_0100100".to_string());
+        let snaks = reference.snaks();
+        assert_eq!(snaks.len(), 4);
+        assert_eq!(snaks[0], Snak::new_item("P248", "Q5531047"));
+        assert_eq!(snaks[1], Snak::new_string("P348", "2019-10"));
+        assert_eq!(
+            snaks[2],
+            Snak::new_string("P854", "https://www.genedb.org/gene/PF3D7_0100100")
+        );
+        assert_eq!(snaks[3].property(), "P813");
+    }
+
     #[test]
     fn test_parent_taxon_q() {
         let mut bot = GeneDBot::new();
@@ -814,6 +1795,43 @@ mod tests {
         assert_eq!(bot.parent_taxon_q(), Some("Q311383".to_string())); // Pf
     }
 
+    #[test]
+    fn test_get_report_filename() {
+        let mut bot = GeneDBot::new();
+        bot.set_species("Pfalciparum");
+        assert_eq!(
+            bot.get_report_filename("orthologs"),
+            "logs/Pfalciparum.orthologs.report"
+        );
+    }
+
+    #[test]
+    fn test_gaf_version_at_least() {
+        let mut bot = GeneDBot::new();
+        assert!(!bot.gaf_version_at_least(2, 2));
+        bot.gaf_version = "2.1".to_string();
+        assert!(bot.gaf_version_at_least(2, 0));
+        assert!(!bot.gaf_version_at_least(2, 2));
+        bot.gaf_version = "2.2".to_string();
+        assert!(bot.gaf_version_at_least(2, 2));
+        bot.gaf_version = "3".to_string();
+        assert!(bot.gaf_version_at_least(2, 2));
+    }
+
+    #[test]
+    fn test_get_go_relation_qualifier() {
+        let mut bot = GeneDBot::new();
+        bot.external_id2q.insert(
+            ("P3590".to_string(), "RO:0002327".to_string()),
+            Some("Q123".to_string()),
+        );
+        assert_eq!(
+            bot.get_go_relation_qualifier("enables"),
+            Some(Snak::new_item(GO_RELATION_QUALIFIER, "Q123"))
+        );
+        assert_eq!(bot.get_go_relation_qualifier("not a relation"), None);
+    }
+
     #[test]
     fn test_set_species() {
         let key = "Pfalciparum";
@@ -821,6 +1839,21 @@ mod tests {
         assert_eq!(bot.species_key, "");
         bot.set_species(key);
         assert_eq!(bot.species_key, key);
+        assert_eq!(
+            bot.species_location("/data/{species}.gpad.gz"),
+            "/data/Pfalciparum.gpad.gz"
+        );
+        bot.assembly_overrides.insert(
+            "pfalciparum.accession".to_string(),
+            "GCA_000002765.3".to_string(),
+        );
+        bot.assembly_overrides
+            .insert("pberghei.version".to_string(), "3".to_string());
+        assert_eq!(
+            bot.get_assembly_override("accession"),
+            Some("GCA_000002765.3".to_string())
+        );
+        assert_eq!(bot.get_assembly_override("version"), None);
     }
 
     #[test]
@@ -0,0 +1,366 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+const OBO_PURL_PREFIX: &str = "http://purl.obolibrary.org/obo/";
+const OBOGRAPHS_REPLACED_BY: &str = "http://purl.obolibrary.org/obo/IAO_0100001";
+const OBOGRAPHS_CONSIDER: &str = "http://www.geneontology.org/formats/oboInOwl#consider";
+const OBOGRAPHS_ALT_ID: &str = "http://www.geneontology.org/formats/oboInOwl#hasAlternativeId";
+const OBOGRAPHS_NAMESPACE: &str = "http://www.geneontology.org/formats/oboInOwl#hasOBONamespace";
+const MAX_REPLACEMENT_DEPTH: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoTerm {
+    pub id: String,
+    pub name: String,
+    /// biological_process, molecular_function or cellular_component
+    pub namespace: String,
+    pub is_obsolete: bool,
+    pub replaced_by: Vec<String>,
+    pub consider: Vec<String>,
+    pub is_a: Vec<String>,
+}
+
+impl GoTerm {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: String::new(),
+            namespace: String::new(),
+            is_obsolete: false,
+            replaced_by: vec![],
+            consider: vec![],
+            is_a: vec![],
+        }
+    }
+}
+
+/// What to do with a GO term from an annotation
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoTermStatus {
+    /// Current term (or not in the ontology file)
+    Current(String),
+    /// Obsolete or secondary term, with a replacement
+    Replaced(String),
+    /// Obsolete term without replacement, with possible alternatives to consider
+    Obsolete(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct GoOntology {
+    pub terms: HashMap<String, GoTerm>,
+    pub alt_id2id: HashMap<String, String>,
+}
+
+impl GoOntology {
+    pub fn new() -> Self {
+        Self {
+            terms: HashMap::new(),
+            alt_id2id: HashMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Loads an ontology from OBO or obographs JSON
+    pub fn load(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let text = String::from_utf8_lossy(data);
+        if text.trim_start().starts_with('{') {
+            self.load_obographs(&serde_json::from_str(&text)?)
+        } else {
+            self.load_obo(&text);
+            Ok(())
+        }
+    }
+
+    pub fn load_obo(&mut self, text: &str) {
+        let mut term: Option<GoTerm> = None;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                self.add_term(term.take());
+                if line == "[Term]" {
+                    term = Some(GoTerm::new(""));
+                }
+                continue;
+            }
+            let t = match term.as_mut() {
+                Some(t) => t,
+                None => continue,
+            };
+            let mut parts = line.splitn(2, ':');
+            let key = parts.next().unwrap_or("").trim();
+            // Remove trailing "! comment"
+            let value = parts
+                .next()
+                .unwrap_or("")
+                .splitn(2, " !")
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_string();
+            match key {
+                "id" => t.id = value,
+                "name" => t.name = value,
+                "namespace" => t.namespace = value,
+                "is_obsolete" => t.is_obsolete = value == "true",
+                "replaced_by" => t.replaced_by.push(value),
+                "consider" => t.consider.push(value),
+                "is_a" => t.is_a.push(value),
+                "alt_id" => {
+                    self.alt_id2id.insert(value, t.id.clone());
+                }
+                _ => {}
+            }
+        }
+        self.add_term(term);
+    }
+
+    pub fn load_obographs(&mut self, j: &serde_json::Value) -> Result<(), Box<dyn Error>> {
+        let graphs = j["graphs"].as_array().ok_or("load_obographs: no graphs")?;
+        for graph in graphs {
+            for node in graph["nodes"].as_array().unwrap_or(&vec![]) {
+                let id = match node["id"].as_str() {
+                    Some(id) => self.normalize_id(id),
+                    None => continue,
+                };
+                if !id.starts_with("GO:") {
+                    continue;
+                }
+                let mut term = GoTerm::new(&id);
+                term.name = node["lbl"].as_str().unwrap_or("").to_string();
+                term.is_obsolete = node["meta"]["deprecated"].as_bool().unwrap_or(false);
+                for pv in node["meta"]["basicPropertyValues"]
+                    .as_array()
+                    .unwrap_or(&vec![])
+                {
+                    let value = match pv["val"].as_str() {
+                        Some(v) => self.normalize_id(v),
+                        None => continue,
+                    };
+                    match pv["pred"].as_str().unwrap_or("") {
+                        OBOGRAPHS_REPLACED_BY => term.replaced_by.push(value),
+                        OBOGRAPHS_CONSIDER => term.consider.push(value),
+                        OBOGRAPHS_NAMESPACE => term.namespace = value,
+                        OBOGRAPHS_ALT_ID => {
+                            self.alt_id2id.insert(value, id.clone());
+                        }
+                        _ => {}
+                    }
+                }
+                self.add_term(Some(term));
+            }
+            for edge in graph["edges"].as_array().unwrap_or(&vec![]) {
+                if edge["pred"].as_str() != Some("is_a") {
+                    continue;
+                }
+                let (sub, obj) = match (edge["sub"].as_str(), edge["obj"].as_str()) {
+                    (Some(sub), Some(obj)) => (self.normalize_id(sub), self.normalize_id(obj)),
+                    _ => continue,
+                };
+                match self.terms.get_mut(&sub) {
+                    Some(term) => term.is_a.push(obj),
+                    None => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_term(&mut self, term: Option<GoTerm>) {
+        match term {
+            Some(term) => {
+                if !term.id.is_empty() {
+                    self.terms.insert(term.id.clone(), term);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Converts "http://purl.obolibrary.org/obo/GO_0000001" into "GO:0000001"
+    pub fn normalize_id(&self, id: &str) -> String {
+        if id.starts_with(OBO_PURL_PREFIX) {
+            id[OBO_PURL_PREFIX.len()..].replacen('_', ":", 1)
+        } else {
+            id.to_string()
+        }
+    }
+
+    /// Returns the term and all its is_a ancestors
+    pub fn get_ancestors(&self, go_id: &str) -> HashSet<String> {
+        let mut ret: HashSet<String> = HashSet::new();
+        let mut todo = vec![go_id.to_string()];
+        while let Some(id) = todo.pop() {
+            if !ret.insert(id.clone()) {
+                continue;
+            }
+            match self.terms.get(&id) {
+                Some(term) => todo.extend(term.is_a.iter().cloned()),
+                None => {}
+            }
+        }
+        ret
+    }
+
+    /// Returns the GO slim terms a term maps to, sorted
+    pub fn get_slim_terms(&self, go_id: &str, slim: &HashSet<String>) -> Vec<String> {
+        let mut ret: Vec<String> = self
+            .get_ancestors(go_id)
+            .intersection(slim)
+            .cloned()
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    /// Reads a GO slim, either as an ontology file (OBO or obographs JSON), or as a list of GO IDs, one per line
+    pub fn load_slim(data: &[u8]) -> Result<HashSet<String>, Box<dyn Error>> {
+        let text = String::from_utf8_lossy(data);
+        if text.trim_start().starts_with('{') || text.contains("[Term]") {
+            let mut slim = GoOntology::new();
+            slim.load(data)?;
+            return Ok(slim.terms.keys().cloned().collect());
+        }
+        Ok(text
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .filter(|id| id.starts_with("GO:"))
+            .map(|id| id.to_string())
+            .collect())
+    }
+
+    /// Returns the GO aspect (P/F/C) of a term, from its namespace
+    pub fn get_aspect(&self, go_id: &str) -> Option<String> {
+        let id = self
+            .alt_id2id
+            .get(go_id)
+            .map(|s| s.as_str())
+            .unwrap_or(go_id);
+        let aspect = match self.terms.get(id)?.namespace.as_str() {
+            "biological_process" => "P",
+            "molecular_function" => "F",
+            "cellular_component" => "C",
+            _ => return None,
+        };
+        Some(aspect.to_string())
+    }
+
+    /// Checks a GO term against the ontology, following alt_id and replaced_by
+    pub fn get_status(&self, go_id: &str) -> GoTermStatus {
+        let mut current = go_id.to_string();
+        for _ in 0..MAX_REPLACEMENT_DEPTH {
+            match self.alt_id2id.get(&current) {
+                Some(id) => current = id.to_string(),
+                None => {}
+            }
+            let term = match self.terms.get(&current) {
+                Some(term) => term,
+                None => break,
+            };
+            if !term.is_obsolete {
+                break;
+            }
+            match term.replaced_by.get(0) {
+                Some(replacement) => current = replacement.to_string(),
+                None => return GoTermStatus::Obsolete(term.consider.clone()),
+            }
+        }
+        if current == go_id {
+            GoTermStatus::Current(current)
+        } else {
+            GoTermStatus::Replaced(current)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OBO: &str = "format-version: 1.2\n\n[Term]\nid: GO:0000001\nname: mitochondrion inheritance\nnamespace: biological_process\nalt_id: GO:0000002\nis_a: GO:0048308 ! organelle inheritance\n\n[Term]\nid: GO:0000003\nname: obsolete thing\nis_obsolete: true\nreplaced_by: GO:0000004\n\n[Term]\nid: GO:0000004\nname: replacement\nis_obsolete: true\nreplaced_by: GO:0000001\n\n[Term]\nid: GO:0000005\nname: obsolete other thing\nis_obsolete: true\nconsider: GO:0000001\nconsider: GO:0000006\n\n[Typedef]\nid: part_of\n";
+
+    #[test]
+    fn test_load_obo() {
+        let mut o = GoOntology::new();
+        o.load(OBO.as_bytes()).unwrap();
+        assert_eq!(o.terms.len(), 4);
+        let term = o.terms.get("GO:0000001").unwrap();
+        assert_eq!(term.name, "mitochondrion inheritance");
+        assert_eq!(term.is_a, vec!["GO:0048308"]);
+        assert_eq!(o.get_aspect("GO:0000002"), Some("P".to_string()));
+        assert_eq!(o.get_aspect("GO:0000003"), None);
+        assert!(o.terms.get("GO:0000005").unwrap().is_obsolete);
+        assert_eq!(
+            o.alt_id2id.get("GO:0000002"),
+            Some(&"GO:0000001".to_string())
+        );
+    }
+
+    #[test]
+    fn test_load_obographs() {
+        let j = json!({"graphs":[{"nodes":[
+            {"id":"http://purl.obolibrary.org/obo/GO_0000001","lbl":"mitochondrion inheritance","meta":{"basicPropertyValues":[{"pred":"http://www.geneontology.org/formats/oboInOwl#hasOBONamespace","val":"biological_process"}]}},
+            {"id":"http://purl.obolibrary.org/obo/GO_0000003","lbl":"obsolete thing","meta":{"deprecated":true,"basicPropertyValues":[{"pred":"http://purl.obolibrary.org/obo/IAO_0100001","val":"GO:0000001"}]}}
+        ],"edges":[{"sub":"http://purl.obolibrary.org/obo/GO_0000001","pred":"is_a","obj":"http://purl.obolibrary.org/obo/GO_0048308"}]}]});
+        let mut o = GoOntology::new();
+        o.load(j.to_string().as_bytes()).unwrap();
+        assert_eq!(o.terms.get("GO:0000001").unwrap().is_a, vec!["GO:0048308"]);
+        assert_eq!(o.get_aspect("GO:0000001"), Some("P".to_string()));
+        assert_eq!(
+            o.get_status("GO:0000003"),
+            GoTermStatus::Replaced("GO:0000001".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_slim_terms() {
+        let mut o = GoOntology::new();
+        o.load_obo(
+            "[Term]\nid: GO:1\n\n[Term]\nid: GO:2\nis_a: GO:1\n\n[Term]\nid: GO:3\nis_a: GO:2\n",
+        );
+        assert_eq!(o.get_ancestors("GO:3").len(), 3);
+        let slim: HashSet<String> = vec!["GO:1".to_string(), "GO:2".to_string()]
+            .into_iter()
+            .collect();
+        assert_eq!(o.get_slim_terms("GO:3", &slim), vec!["GO:1", "GO:2"]);
+        assert_eq!(o.get_slim_terms("GO:1", &slim), vec!["GO:1"]);
+        assert!(o.get_slim_terms("GO:9", &slim).is_empty());
+    }
+
+    #[test]
+    fn test_load_slim() {
+        let slim = GoOntology::load_slim(b"GO:0000001 ! something\n\nGO:0000002\n").unwrap();
+        assert_eq!(slim.len(), 2);
+        assert!(slim.contains("GO:0000001"));
+        let slim = GoOntology::load_slim(OBO.as_bytes()).unwrap();
+        assert_eq!(slim.len(), 4);
+    }
+
+    #[test]
+    fn test_get_status() {
+        let mut o = GoOntology::new();
+        o.load_obo(OBO);
+        assert_eq!(
+            o.get_status("GO:0000001"),
+            GoTermStatus::Current("GO:0000001".to_string())
+        );
+        assert_eq!(
+            o.get_status("GO:0000002"),
+            GoTermStatus::Replaced("GO:0000001".to_string())
+        );
+        assert_eq!(
+            o.get_status("GO:0000003"),
+            GoTermStatus::Replaced("GO:0000001".to_string())
+        );
+        assert_eq!(
+            o.get_status("GO:0000005"),
+            GoTermStatus::Obsolete(vec!["GO:0000001".to_string(), "GO:0000006".to_string()])
+        );
+        assert_eq!(
+            o.get_status("GO:9999999"),
+            GoTermStatus::Current("GO:9999999".to_string())
+        );
+    }
+}
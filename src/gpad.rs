@@ -0,0 +1,289 @@
+use crate::genedbot::*;
+use crate::loader::{add_gaf_record, open_url_or_file, warn_on_unmatched_gaf_taxa};
+use bio::io::gaf;
+use regex::Regex;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::BufRead;
+
+/// Gene product information from a GPI 2.0 file
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpiEntry {
+    pub db: String,
+    pub db_object_id: String,
+    pub symbol: String,
+    pub name: String,
+    pub synonyms: Vec<String>,
+    pub object_type: String,
+    pub taxon: String,
+}
+
+/// Splits "GeneDB:PF3D7_0100100.1" into ("GeneDB","PF3D7_0100100.1")
+fn split_curie(s: &str) -> (String, String) {
+    let mut parts = s.trim().splitn(2, ':');
+    let first = parts.next().unwrap_or("").to_string();
+    match parts.next() {
+        Some(second) => (first, second.to_string()),
+        None => ("".to_string(), first),
+    }
+}
+
+/// Converts "NCBITaxon:9606" into the GAF "taxon:9606"
+fn gaf_taxon(s: &str) -> String {
+    let (_db, id) = split_curie(s);
+    format!("taxon:{}", id)
+}
+
+/// Converts "2019-03-10" or "2019-03-10T12:00:00" into the GAF "20190310"
+fn gaf_date(s: &str) -> Option<String> {
+    lazy_static! {
+        static ref RE_DATE: Regex =
+            Regex::new(r"^(\d{4})-?(\d{2})-?(\d{2})$").expect("gaf_date: RE_DATE does not compile");
+    }
+    let date = s.trim().splitn(2, 'T').next()?;
+    let caps = RE_DATE.captures(date)?;
+    Some(format!("{}{}{}", &caps[1], &caps[2], &caps[3]))
+}
+
+fn is_comment_or_empty(line: &str) -> bool {
+    line.starts_with('!') || line.trim().is_empty()
+}
+
+/// Calls f for every line of the stream that is not a comment or empty
+fn for_each_data_line(
+    stream: &mut dyn BufRead,
+    mut f: impl FnMut(&str),
+) -> Result<(), Box<dyn Error>> {
+    for line in BufRead::split(stream, b'\n') {
+        let line = line?;
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches('\r');
+        if !is_comment_or_empty(line) {
+            f(line);
+        }
+    }
+    Ok(())
+}
+
+fn parse_gpi_line(line: &str) -> Option<GpiEntry> {
+    let cols: Vec<&str> = line.split('\t').collect();
+    if cols.len() < 6 {
+        return None;
+    }
+    let (db, db_object_id) = split_curie(cols[0]);
+    Some(GpiEntry {
+        db,
+        db_object_id,
+        symbol: cols[1].to_string(),
+        name: cols[2].to_string(),
+        synonyms: cols[3]
+            .split('|')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect(),
+        object_type: cols[4].to_string(),
+        taxon: gaf_taxon(cols[5]),
+    })
+}
+
+/// Reads a GPI 2.0 stream, keyed by the DB object ID
+pub fn parse_gpi(stream: &mut dyn BufRead) -> Result<HashMap<String, GpiEntry>, Box<dyn Error>> {
+    let mut ret = HashMap::new();
+    for_each_data_line(stream, |line| match parse_gpi_line(line) {
+        Some(entry) => {
+            ret.insert(entry.db_object_id.clone(), entry);
+        }
+        None => {}
+    })?;
+    Ok(ret)
+}
+
+/// Returns the GO aspect (P/F/C) for a GO term from the GO ontology, if loaded; otherwise, from the GPAD relation
+fn get_aspect(bot: &GeneDBot, go_id: &str, relation: &str) -> Option<String> {
+    match bot.go_ontology.get_aspect(go_id) {
+        Some(aspect) => return Some(aspect),
+        None => {}
+    }
+    let aspect = match relation {
+        "enables" | "contributes_to" => "F",
+        "located_in" | "part_of" | "is_active_in" | "colocalizes_with" => "C",
+        other => {
+            if bot.go_relations.contains_key(other) {
+                "P"
+            } else {
+                return None;
+            }
+        }
+    };
+    Some(aspect.to_string())
+}
+
+/// Converts a GPAD 2.0 line into a GAF record, using the GPI entry for the gene product, if any.
+/// Evidence is kept as an ECO ID.
+pub fn gpad_line_to_gaf_record(
+    bot: &GeneDBot,
+    line: &str,
+    gpi: &HashMap<String, GpiEntry>,
+) -> Result<gaf::Record, String> {
+    let cols: Vec<&str> = line.split('\t').collect();
+    if cols.len() < 10 {
+        return Err(format!("Not enough GPAD columns: '{}'", line));
+    }
+    let (db, db_object_id) = split_curie(cols[0]);
+    let relation = bot
+        .go_relations
+        .iter()
+        .filter(|(_, ro_id)| ro_id.as_str() == cols[2].trim())
+        .map(|(label, _)| label.to_owned())
+        .next()
+        .ok_or(format!("Unknown GPAD relation '{}'", cols[2]))?;
+    let go_id = cols[3].trim().to_string();
+    let aspect = get_aspect(bot, &go_id, &relation).ok_or(format!(
+        "No GO aspect for '{}' with relation '{}'",
+        &go_id, &relation
+    ))?;
+    let date = gaf_date(cols[8]).ok_or(format!("Invalid GPAD date '{}'", cols[8]))?;
+
+    let mut record = gaf::Record::new();
+    *record.db_mut() = db;
+    *record.db_object_id_mut() = db_object_id.clone();
+    if cols[1].trim() == "NOT" {
+        record.qualifier_mut().push("NOT".to_string());
+    }
+    record.qualifier_mut().push(relation);
+    *record.go_id_mut() = go_id;
+    cols[4]
+        .split('|')
+        .filter(|s| !s.is_empty())
+        .map(|s| split_curie(s))
+        .for_each(|(k, v)| record.db_ref_mut().insert(k, v));
+    *record.evidence_code_mut() = cols[5].trim().to_string();
+    cols[6]
+        .split(|c| c == '|' || c == ',')
+        .filter(|s| !s.is_empty())
+        .for_each(|s| record.with_from_mut().push(s.to_string()));
+    *record.aspect_mut() = aspect;
+    *record.date_mut() = date;
+    *record.assigned_by_mut() = cols[9].trim().to_string();
+    match cols.get(10) {
+        Some(ext) => {
+            if !ext.trim().is_empty() {
+                record
+                    .annotation_extension_mut()
+                    .push(ext.trim().to_string());
+            }
+        }
+        None => {}
+    }
+
+    match gpi.get(&db_object_id) {
+        Some(entry) => {
+            *record.db_object_symbol_mut() = entry.symbol.clone();
+            *record.db_object_name_mut() = entry.name.clone();
+            *record.db_object_synonym_mut() = entry.synonyms.clone();
+            *record.db_object_type_mut() = entry.object_type.clone();
+            record.taxon_mut().push(entry.taxon.clone());
+        }
+        None => {
+            // Without GPI, the gene product is assumed to be from the species being run
+            if !bot.config.taxon_id.is_empty() {
+                record
+                    .taxon_mut()
+                    .push(format!("taxon:{}", &bot.config.taxon_id));
+            }
+        }
+    }
+    if !cols[7].trim().is_empty() {
+        record.taxon_mut().push(gaf_taxon(cols[7]));
+    }
+    Ok(record)
+}
+
+/// Loads GPAD (and, optionally, GPI) files into bot.gaf, instead of a GAF file
+pub fn load_gpad_file(
+    bot: &mut GeneDBot,
+    gpad_location: &str,
+    gpi_location: &Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let gpi = match gpi_location {
+        Some(location) => parse_gpi(&mut open_url_or_file(location)?)?,
+        None => HashMap::new(),
+    };
+    let mut gpad = open_url_or_file(gpad_location)?;
+    for_each_data_line(&mut gpad, |line| {
+        match gpad_line_to_gaf_record(bot, line, &gpi) {
+            Ok(record) => add_gaf_record(bot, record),
+            Err(e) => bot.log(&gpad_location.to_string(), &e),
+        }
+    })?;
+    warn_on_unmatched_gaf_taxa(bot, gpad_location);
+    if bot.gaf.is_empty() && !bot.allow_empty_gaf {
+        return Err(From::from(format!(
+            "Can't get GPAD data from {}",
+            gpad_location
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_curie() {
+        assert_eq!(
+            split_curie("GeneDB:PF3D7_0100100.1"),
+            ("GeneDB".to_string(), "PF3D7_0100100.1".to_string())
+        );
+        assert_eq!(
+            split_curie("ECO:0000250"),
+            ("ECO".to_string(), "0000250".to_string())
+        );
+    }
+
+    #[test]
+    fn test_gaf_taxon_and_date() {
+        assert_eq!(gaf_taxon("NCBITaxon:36329"), "taxon:36329");
+        assert_eq!(gaf_date("2019-03-10"), Some("20190310".to_string()));
+        assert_eq!(
+            gaf_date("2019-03-10T15:02:01"),
+            Some("20190310".to_string())
+        );
+        assert_eq!(gaf_date("20190310"), Some("20190310".to_string()));
+        assert_eq!(gaf_date("2019-03"), None);
+    }
+
+    #[test]
+    fn test_parse_gpi() {
+        let gpi = parse_gpi(&mut "!gpi-version: 2.0\r\nGeneDB:PF3D7_0100100.1\tVAR\terythrocyte membrane protein 1, PfEMP1\tPFA0005w|VAR-UPSB1\tSO:0000234\tNCBITaxon:36329\t\t\t\t\t\r\n".as_bytes()).unwrap();
+        let entry = gpi.get("PF3D7_0100100.1").unwrap();
+        assert_eq!(entry.db, "GeneDB");
+        assert_eq!(entry.symbol, "VAR");
+        assert_eq!(entry.synonyms, vec!["PFA0005w", "VAR-UPSB1"]);
+        assert_eq!(entry.taxon, "taxon:36329");
+    }
+
+    #[test]
+    fn test_gpad_line_to_gaf_record() {
+        let bot = GeneDBot::new();
+        let gpi = parse_gpi(&mut "GeneDB:PF3D7_0100100.1\tVAR\terythrocyte membrane protein 1, PfEMP1\t\tSO:0000234\tNCBITaxon:36329\n".as_bytes()).unwrap();
+        let line = "GeneDB:PF3D7_0100100.1\t\tRO:0002327\tGO:0050839\tPMID:9786187\tECO:0000304\t\t\t2019-06-01\tGeneDB\t\t";
+        let record = gpad_line_to_gaf_record(&bot, line, &gpi).unwrap();
+        assert_eq!(record.db_object_id(), "PF3D7_0100100.1");
+        assert_eq!(record.go_id(), "GO:0050839");
+        assert_eq!(record.evidence_code(), "ECO:0000304");
+        assert_eq!(record.aspect(), "F");
+        let mut bot = bot;
+        bot.go_ontology
+            .load_obo("[Term]\nid: GO:0050839\nnamespace: biological_process\n");
+        let record = gpad_line_to_gaf_record(&bot, line, &gpi).unwrap();
+        assert_eq!(record.aspect(), "P");
+        assert_eq!(record.date(), "20190601");
+        assert_eq!(
+            record.db_object_name(),
+            "erythrocyte membrane protein 1, PfEMP1"
+        );
+        assert!(gpad_line_to_gaf_record(&bot, "GeneDB:X\tfoo", &gpi).is_err());
+    }
+}
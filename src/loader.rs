@@ -2,11 +2,20 @@ use crate::genedbot::*;
 use std::sync::{Arc, RwLock};
 //use reqwest::header::USER_AGENT;
 //use crate::{GeneDBot, Toolbox};
+use crate::assembly::*;
+use crate::go_ontology::GoOntology;
+use crate::gpad;
+use crate::protection::ItemKind;
+use crate::proteome;
+use crate::sequence_region::{SequenceRegion, SEQUENCE_TYPES};
+use crate::transcript;
 use bio::io::{gaf, gff};
 use libflate::gzip::Decoder;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
 use wikibase::entity_diff::*;
 use wikibase::*;
 
@@ -25,7 +34,9 @@ pub static TEST_URL_GAF_GZ: &str = "http://magnusmanske.de/genedbot/test.gaf.gz"
 
 pub fn init(bot: &mut GeneDBot) -> Result<(), Box<dyn Error>> {
     load_gff_file(bot)?; //.expect(&format!("Can't load GFF file '{}'", gff_url(bot)));
+    load_go_ontology(bot)?; // Before GPAD, for the GO aspects
     load_gaf_file(bot)?; //.expect(&format!("Can't load GAF file '{}'", gaf_url(bot)));
+    load_protein_fasta(bot)?;
     find_genomic_assembly(bot, true)?;
     load_basic_items(bot)?;
     Ok(())
@@ -35,6 +46,28 @@ pub fn get_text_from_url(url: &str) -> Result<String, reqwest::Error> {
     Ok(reqwest::blocking::get(url)?.text()?)
 }
 
+/// Opens a local file or a remote URL as a stream; gzipped content is decompressed
+pub fn open_url_or_file(location: &str) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    let raw: Box<dyn Read> = if location.contains("://") {
+        Box::new(reqwest::blocking::get(location)?)
+    } else {
+        Box::new(fs::File::open(location)?)
+    };
+    let mut stream = BufReader::new(raw);
+    if stream.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        Ok(Box::new(BufReader::new(Decoder::new(stream)?)))
+    } else {
+        Ok(Box::new(stream))
+    }
+}
+
+/// Reads a local file or a remote URL; gzipped content is decompressed
+pub fn read_url_or_file(location: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut ret: Vec<u8> = vec![];
+    open_url_or_file(location)?.read_to_end(&mut ret)?;
+    Ok(ret)
+}
+
 pub fn get_json_from_url(url: &str) -> Result<serde_json::Value, reqwest::Error> {
     Ok(reqwest::blocking::get(url)?.json()?)
 }
@@ -55,13 +88,17 @@ pub fn gaf_url(bot: &GeneDBot) -> String {
 
 pub fn load_gff_file_from_url(bot: &mut GeneDBot, url: &str) -> Result<(), Box<dyn Error>> {
     let mut orth_ids: HashSet<String> = HashSet::new();
-    let mut res = reqwest::blocking::get(url)?;
-    let decoder = Decoder::new(&mut res)?;
-    let mut reader = gff::Reader::new(decoder, gff::GffType::GFF3);
+    let mut orth_programs: HashSet<String> = HashSet::new();
+    let res = reqwest::blocking::get(url)?;
+    let mut stream = BufReader::new(Decoder::new(res)?);
+    // The header lines read are passed on to the GFF reader
+    let header = read_header_lines(&mut stream, b'#')?;
+    load_gff_directives(bot, &header);
+    let mut reader = gff::Reader::new(header.as_slice().chain(stream), gff::GffType::GFF3);
     for element in reader.records() {
         match element {
             Ok(e) => {
-                process_gff_element(bot, &e, &mut orth_ids);
+                process_gff_element(bot, &e, &mut orth_ids, &mut orth_programs);
             }
             _ => continue,
         }
@@ -70,9 +107,40 @@ pub fn load_gff_file_from_url(bot: &mut GeneDBot, url: &str) -> Result<(), Box<d
     if bot.gff.is_empty() {
         return Err(From::from(format!("Can't get GFF data from {}", url)));
     }
+    let unknown_programs = bot
+        .orthologs
+        .load_programs(&bot.api.read().unwrap(), orth_programs)?;
+    unknown_programs
+        .iter()
+        .for_each(|program| bot.log(program, "No Wikidata item for ortholog program"));
     bot.orthologs.load(&bot.api.read().unwrap(), orth_ids)
 }
 
+/// Reads lines from the stream up to and including the first line that does not start with the comment character
+fn read_header_lines(stream: &mut dyn BufRead, comment: u8) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut header: Vec<u8> = vec![];
+    loop {
+        let line_start = header.len();
+        if stream.read_until(b'\n', &mut header)? == 0 || header[line_start] != comment {
+            break;
+        }
+    }
+    Ok(header)
+}
+
+/// Reads the "##sequence-region" directives and "#!genome-..." pragmas; the GFF reader skips them
+fn load_gff_directives(bot: &mut GeneDBot, data: &[u8]) {
+    String::from_utf8_lossy(data)
+        .lines()
+        .take_while(|line| line.starts_with('#'))
+        .for_each(|line| match SequenceRegion::new_from_directive(line) {
+            Some((id, region)) => {
+                bot.sequence_regions.insert(id, region);
+            }
+            None => bot.assembly.update_from_gff_header_line(line),
+        });
+}
+
 fn fix_id(id: &str) -> String {
     lazy_static! {
         static ref RE1: Regex = Regex::new(r":.*$").unwrap();
@@ -98,6 +166,7 @@ fn process_gff_element(
     bot: &mut GeneDBot,
     element: &bio::io::gff::Record,
     orth_ids: &mut HashSet<String>,
+    orth_programs: &mut HashSet<String>,
 ) {
     lazy_static! {
         static ref VALID_GENE_TYPES: Vec<&'static str> = vec!["gene", "mRNA", "pseudogene"];
@@ -106,6 +175,14 @@ fn process_gff_element(
     if element.attributes().contains_key("ID") {
         let id = element.attributes()["ID"].clone();
         bot.gff.insert(id.clone(), element.clone());
+        if id == element.seqname() && !element.attributes().contains_key("Parent") {
+            // Record of the sequence (chromosome, contig...) itself
+            let has_directive = bot.sequence_regions.contains_key(&id);
+            bot.sequence_regions
+                .entry(id.clone())
+                .or_insert(SequenceRegion::new(*element.start(), *element.end()))
+                .update_from_record(element, has_directive);
+        }
         match element.attributes().get("Parent") {
             Some(parent_id) => bot
                 .parent2child
@@ -164,41 +241,133 @@ fn process_gff_element(
     }
 
     bot.orthologs
-        .get_from_gff_element(&element)
-        .iter()
-        .for_each(|x| {
-            orth_ids.insert(x.1.to_owned());
+        .get_ids_to_load_from_gff_element(&element)
+        .into_iter()
+        .for_each(|id| {
+            orth_ids.insert(id);
+        });
+    bot.orthologs
+        .get_programs_from_gff_element(&element)
+        .into_iter()
+        .for_each(|program| {
+            orth_programs.insert(program);
         });
 }
 
+/// Returns the GAF version from the "!gaf-version" header, or an empty string
+fn get_gaf_version(buffer: &[u8]) -> String {
+    lazy_static! {
+        static ref RE_VERSION: Regex = Regex::new(r"^!\s*gaf-version:\s*(\S+)").unwrap();
+    }
+    for line in buffer.split(|c| *c == b'\n') {
+        if !line.starts_with(b"!") {
+            break;
+        }
+        let line = String::from_utf8_lossy(line);
+        match RE_VERSION.captures(&line) {
+            Some(caps) => return caps[1].to_string(),
+            None => {}
+        }
+    }
+    "".to_string()
+}
+
 pub fn load_gaf_file_from_url(bot: &mut GeneDBot, url: &str) -> Result<(), Box<dyn Error>> {
-    let mut res = reqwest::blocking::get(url)?;
-    let decoder = Decoder::new(&mut res)?;
-    let mut reader = gaf::Reader::new(decoder, gaf::GafType::GAF2);
+    let res = reqwest::blocking::get(url)?;
+    let mut stream = BufReader::new(Decoder::new(res)?);
+    // Reads the header for the GAF version; the lines read are passed on to the GAF reader
+    let header = read_header_lines(&mut stream, b'!')?;
+    bot.gaf_version = get_gaf_version(&header);
+    // GAF 2.2 has the same columns as GAF 2.1, but with a relation in the qualifier column
+    let mut reader = gaf::Reader::new(header.as_slice().chain(stream), gaf::GafType::GAF2);
     for element in reader.records() {
         match element {
-            Ok(e) => {
-                let id = e.db_object_id().to_string();
-                if !bot.gaf.contains_key(&id) {
-                    bot.gaf.insert(id.clone(), vec![]);
-                }
-                bot.gaf.get_mut(&id).unwrap().push(e);
-            }
+            Ok(e) => add_gaf_record(bot, e),
             _ => continue,
         }
     }
+    warn_on_unmatched_gaf_taxa(bot, url);
     if bot.gaf.is_empty() && !bot.allow_empty_gaf {
         return Err(From::from(format!("Can't get GAF data from {}", url)));
     }
     Ok(())
 }
 
+/// Adds a GAF row to bot.gaf, if it belongs to the species being run; logs it otherwise
+pub fn add_gaf_record(bot: &mut GeneDBot, record: gaf::Record) {
+    let id = record.db_object_id().to_string();
+    match bot.check_gaf_record(&record) {
+        Ok(_) => bot.gaf.entry(id).or_insert(vec![]).push(record),
+        Err(e) => {
+            match record.taxon().get(0) {
+                Some(taxon) => {
+                    if !bot.accepted_gaf_taxa().contains(taxon) {
+                        bot.gaf_skipped_taxa.insert(taxon.to_string());
+                    }
+                }
+                None => {}
+            }
+            bot.log(&id, &format!("{}, row for '{}' skipped", e, record.go_id()))
+        }
+    }
+}
+
+/// Warns once if no GAF row was for the species taxa, with the taxa found instead
+pub fn warn_on_unmatched_gaf_taxa(bot: &GeneDBot, location: &str) {
+    if !bot.gaf.is_empty() || bot.gaf_skipped_taxa.is_empty() {
+        return;
+    }
+    let mut found: Vec<String> = bot.gaf_skipped_taxa.iter().cloned().collect();
+    found.sort();
+    let message = format!(
+        "No GAF row for {}, found {}; equivalent taxa can be set in [gaf_taxa]",
+        bot.accepted_gaf_taxa().join("/"),
+        found.join(", ")
+    );
+    println!("{}: {}", location, &message);
+    bot.log(&location.to_string(), &message);
+}
+
+/// Loads the GO ontology file (OBO or obographs JSON) and the GO slim, if set
+pub fn load_go_ontology(bot: &mut GeneDBot) -> Result<(), Box<dyn Error>> {
+    match bot.go_ontology_file.clone() {
+        Some(location) => bot.go_ontology.load(&read_url_or_file(&location)?)?,
+        None => {}
+    }
+    match bot.go_slim_file.clone() {
+        Some(location) => bot.go_slim = GoOntology::load_slim(&read_url_or_file(&location)?)?,
+        None => {}
+    }
+    if bot.go_rollup == GoRollup::Slim && (bot.go_slim.is_empty() || bot.go_ontology.is_empty()) {
+        return Err(From::from("GO slim rollup needs go_ontology and go_slim"));
+    }
+    Ok(())
+}
+
+pub fn load_protein_fasta(bot: &mut GeneDBot) -> Result<(), Box<dyn Error>> {
+    match bot.protein_fasta_file.clone() {
+        Some(location) => {
+            let location = bot.species_location(&location);
+            bot.protein_sequences = proteome::load_fasta(&read_url_or_file(&location)?)?
+        }
+        None => {}
+    }
+    Ok(())
+}
+
 pub fn load_gff_file(bot: &mut GeneDBot) -> Result<(), Box<dyn Error>> {
     load_gff_file_from_url(bot, gff_url(bot).as_str())
 }
 
 pub fn load_gaf_file(bot: &mut GeneDBot) -> Result<(), Box<dyn Error>> {
-    load_gaf_file_from_url(bot, gaf_url(bot).as_str())
+    match bot.gpad_file.clone() {
+        Some(gpad_file) => {
+            let gpad_file = bot.species_location(&gpad_file);
+            let gpi_file = bot.gpi_file.clone().map(|f| bot.species_location(&f));
+            gpad::load_gpad_file(bot, &gpad_file, &gpi_file)
+        }
+        None => load_gaf_file_from_url(bot, gaf_url(bot).as_str()),
+    }
 }
 
 fn create_genomic_assembly_item(bot: &mut GeneDBot) -> Result<Entity, Box<dyn Error>> {
@@ -218,11 +387,16 @@ fn create_genomic_assembly_item(bot: &mut GeneDBot) -> Result<Entity, Box<dyn Er
         None => return err1,
     };
 
+    let info = get_assembly_info(bot);
+    let label = match &info.version {
+        Some(version) => format!("{} reference genome ({})", taxon_name, version),
+        None => taxon_name + " reference genome",
+    };
     let qualifiers = vec![];
     let mut new_item = Entity::new_empty_item();
-    new_item.set_label(LocaleString::new("en", &(taxon_name + " reference genome")));
+    new_item.set_label(LocaleString::new("en", &label));
     new_item.add_claim(Statement::new_normal(
-        Snak::new_item("P279", "Q7307127"),
+        Snak::new_item("P279", GENOMIC_ASSEMBLY_Q),
         qualifiers.clone(),
         bot.references(),
     ));
@@ -231,18 +405,85 @@ fn create_genomic_assembly_item(bot: &mut GeneDBot) -> Result<Entity, Box<dyn Er
         qualifiers.clone(),
         bot.references(),
     ));
+    for snak in info.snaks() {
+        new_item.add_claim(Statement::new_normal(
+            snak,
+            qualifiers.clone(),
+            bot.references(),
+        ));
+    }
     Ok(new_item)
 }
 
-fn create_genomic_assembly(bot: &mut GeneDBot) -> Result<String, Box<dyn Error>> {
-    let new_item = create_genomic_assembly_item(bot)?;
+fn create_genomic_assembly(
+    bot: &mut GeneDBot,
+    replaces: &Vec<String>,
+) -> Result<String, Box<dyn Error>> {
+    let mut new_item = create_genomic_assembly_item(bot)?;
+    for old_q in replaces {
+        new_item.add_claim(Statement::new_normal(
+            Snak::new_item("P1365", old_q), // Replaces
+            vec![],
+            bot.references(),
+        ));
+    }
     let params = EntityDiffParams::all();
-    let mut diff = EntityDiff::new(&Entity::new_empty_item(), &new_item, &params);
+    let original_item = Entity::new_empty_item();
+    let mut diff = EntityDiff::new(&original_item, &new_item, &params);
     diff.set_edit_summary(bot.get_edit_summary());
+    bot.protect_claims(ItemKind::Assembly, &mut diff, &original_item);
+    let q = match bot.ec.apply_diff(&mut bot.api.write().unwrap(), &diff) {
+        Some(q) => q,
+        None => return Err(From::from("Could not create genomic assembly item")),
+    };
+    for old_q in replaces {
+        add_to_genomic_assembly(bot, old_q, vec![Snak::new_item("P1366", &q)]); // Replaced by
+    }
+    Ok(q)
+}
+
+/// Adds statements to an existing assembly item; nothing is removed
+fn add_to_genomic_assembly(bot: &mut GeneDBot, q: &String, snaks: Vec<Snak>) {
+    let original_item = match bot.ec.load_entity(&bot.api.read().unwrap(), q.to_string()) {
+        Ok(i) => i.clone(),
+        Err(_) => return,
+    };
+    let mut new_item = original_item.clone();
+    for snak in snaks {
+        if original_item
+            .claims()
+            .iter()
+            .any(|claim| *claim.main_snak() == snak)
+        {
+            continue;
+        }
+        new_item.add_claim(Statement::new_normal(snak, vec![], bot.references()));
+    }
+    let mut params = EntityDiffParams::none();
+    params.claims.add = EntityDiffParamState::All;
+    let mut diff = EntityDiff::new(&original_item, &new_item, &params);
+    diff.set_edit_summary(bot.get_edit_summary());
+    if diff.is_empty() || bot.simulate {
+        return;
+    }
     match bot.ec.apply_diff(&mut bot.api.write().unwrap(), &diff) {
-        Some(q) => Ok(q),
-        None => Err(From::from("Could not create genomic assembly item")),
+        Some(_) => {}
+        None => bot.log(q, "Could not update genomic assembly item"),
+    }
+}
+
+/// The assembly version and accession from the GFF header, unless set in the options
+fn get_assembly_info(bot: &GeneDBot) -> AssemblyInfo {
+    let mut info = bot.assembly.clone();
+    match bot.get_assembly_override("version") {
+        Some(version) => info.version = Some(version),
+        None => {}
+    }
+    match bot.get_assembly_override("accession") {
+        Some(accession) => info.accession = Some(accession),
+        None => {}
     }
+    info
 }
 
 fn find_genomic_assembly(
@@ -250,32 +491,46 @@ fn find_genomic_assembly(
     do_create_if_missing: bool,
 ) -> Result<(), Box<dyn Error>> {
     let sparql = format!(
-        "SELECT ?q {{ ?q wdt:P279 wd:Q7307127 ; wdt:P703 wd:{} }}",
+        "SELECT ?q {{ ?q wdt:P279 wd:{} ; wdt:P703 wd:{} }}",
+        GENOMIC_ASSEMBLY_Q,
         bot.species_q()
     );
     let res = bot.api.read().unwrap().sparql_query(&sparql)?;
-    let candidates = bot
+    let candidate_qs = bot
         .api
         .read()
         .unwrap()
         .entities_from_sparql_result(&res, "q");
-    bot.genomic_assembly_q = match candidates.len() {
-        0 => {
+    bot.ec
+        .load_entities(&bot.api.read().unwrap(), &candidate_qs)?;
+    let candidates: Vec<AssemblyCandidate> = candidate_qs
+        .iter()
+        .filter_map(|q| bot.ec.get_entity(q.to_string()))
+        .map(|i| AssemblyCandidate::new_from_entity(&i))
+        .collect();
+    let info = get_assembly_info(bot);
+    bot.genomic_assembly_q = match choose_assembly(&candidates, &info) {
+        AssemblyChoice::Existing(q) => q,
+        AssemblyChoice::Adopt(q) => {
+            add_to_genomic_assembly(bot, &q, info.snaks());
+            q
+        }
+        AssemblyChoice::New(replaces) => {
             if do_create_if_missing {
-                create_genomic_assembly(bot)?
+                create_genomic_assembly(bot, &replaces)?
             } else {
                 return Err(From::from(format!(
-                    "Can't find genomic assembly for {}, and not allowed to create one:\n{}",
+                    "Can't find genomic assembly for {} ({:?}), and not allowed to create one:\n{}",
                     &bot.species_q(),
+                    &info,
                     &sparql
                 )));
             }
         }
-        1 => candidates[0].to_owned(),
-        _ => {
+        AssemblyChoice::Ambiguous => {
             return Err(From::from(format!(
-                "More than one genetic assembly for {}",
-                &sparql
+                "More than one genetic assembly matches {:?} for {}",
+                &info, &sparql
             )));
         }
     };
@@ -283,8 +538,26 @@ fn find_genomic_assembly(
 }
 
 fn load_basic_items_chr(bot: &mut GeneDBot) -> Result<(), Box<dyn Error>> {
+    // Sequence class => sequence type; types sharing a class (eg scaffold/supercontig) use the first one
+    let mut sequence_types: Vec<String> =
+        SEQUENCE_TYPES.iter().map(|(t, _)| t.to_string()).collect();
+    bot.sequence_type_rules
+        .iter()
+        .for_each(|rule| sequence_types.push(rule.sequence_type.to_owned()));
+    let mut class2type: HashMap<String, String> = HashMap::new();
+    for sequence_type in sequence_types {
+        match bot.get_sequence_class_q(&sequence_type) {
+            Some(q) => {
+                class2type.entry(q).or_insert(sequence_type);
+            }
+            None => {}
+        }
+    }
+    let classes: Vec<String> = class2type.keys().cloned().collect();
+
     let sparql = format!(
-        "SELECT ?q {{ ?q wdt:P31 wd:Q37748 ; wdt:P703 wd:{} }}",
+        "SELECT DISTINCT ?q {{ VALUES ?class {{ wd:{} }} . ?q wdt:P31 ?class ; wdt:P703 wd:{} }}",
+        classes.join(" wd:"),
         &bot.species_q()
     );
     let res = bot.api.read().unwrap().sparql_query(&sparql)?;
@@ -301,12 +574,16 @@ fn load_basic_items_chr(bot: &mut GeneDBot) -> Result<(), Box<dyn Error>> {
         .iter()
         .for_each(|q| match bot.ec.get_entity(q.to_string()) {
             Some(i) => {
-                if i.has_target_entity("P31", "Q37748") {
-                    match i.label_in_locale("en") {
-                        Some(label) => {
-                            bot.chr2q.insert(label.to_string(), q.to_string());
-                        }
-                        None => {}
+                let label = match i.label_in_locale("en") {
+                    Some(label) => label.to_string(),
+                    None => return,
+                };
+                for (class_q, sequence_type) in &class2type {
+                    if i.has_target_entity("P31", class_q) {
+                        bot.seq2q
+                            .entry(sequence_type.to_owned())
+                            .or_insert(HashMap::new())
+                            .insert(label.to_owned(), q.to_string());
                     }
                 }
             }
@@ -379,6 +656,23 @@ pub fn load_basic_items_genes(bot: &mut GeneDBot) -> Result<(), Box<dyn Error>>
         sparql_result_to_pairs(bot.api.clone(), &res["results"]["bindings"], "genedb", "q")
             .into_iter()
             .collect();
+
+    // Transcripts have no GeneDB ID (P3382, used by their protein); they are found by their English label (the transcript ID)
+    // and the GeneDB gene they are part of
+    if bot.transcript_items {
+        let transcript_types: Vec<String> = transcript::TRANSCRIPT_TYPES
+            .iter()
+            .filter_map(|(_, so_id)| bot.get_item_for_external_id("P3986", so_id))
+            .collect();
+        if !transcript_types.is_empty() {
+            let sparql = format!("SELECT DISTINCT ?q ?genedb {{ {} . VALUES ?transcript_types {{ wd:{} }} . ?q wdt:P31 ?transcript_types ; wdt:P703 ?species ; wdt:P361 ?gene ; rdfs:label ?genedb . ?gene wdt:P3382 [] . FILTER ( LANG(?genedb) = 'en' ) }}",&species_list,transcript_types.join(" wd:")) ;
+            let res = bot.api.read().unwrap().sparql_query(&sparql)?;
+            bot.transcript_genedb2q =
+                sparql_result_to_pairs(bot.api.clone(), &res["results"]["bindings"], "genedb", "q")
+                    .into_iter()
+                    .collect();
+        }
+    }
     Ok(())
 }
 
@@ -390,6 +684,7 @@ fn get_gene_entities_to_process(bot: &GeneDBot) -> Vec<String> {
             .iter()
             .map(|(_, v)| v.to_owned())
             .chain(bot.protein_genedb2q.iter().map(|(_, v)| v.to_owned()))
+            .chain(bot.transcript_genedb2q.iter().map(|(_, v)| v.to_owned()))
             .collect(),
     }
 }
@@ -552,6 +847,7 @@ mod tests {
         let mut bot = GeneDBot::new();
         load_gaf_file_from_url(&mut bot, TEST_URL_GAF_GZ).unwrap();
         assert!(bot.gaf.contains_key("PF3D7_0100100.1"));
+        assert_eq!(bot.gaf_version, "1.0");
         assert_eq!(
             bot.gaf
                 .get("PF3D7_0100100.1")
@@ -564,6 +860,19 @@ mod tests {
         // Just testing correct loading, not testing GAF parsing any further here
     }
 
+    #[test]
+    fn test_get_gaf_version() {
+        assert_eq!(
+            get_gaf_version(b"!gaf-version: 2.2\n!generated-by: GeneDB\nGeneDB\tPF3D7_0100100.1"),
+            "2.2"
+        );
+        assert_eq!(get_gaf_version(b"!gaf-version:2.1\n"), "2.1");
+        assert_eq!(
+            get_gaf_version(b"GeneDB\tPF3D7_0100100.1\n!gaf-version: 2.2\n"),
+            ""
+        );
+    }
+
     #[test]
     fn test_load_gff_file_from_url() {
         let mut bot = GeneDBot::new();
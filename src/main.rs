@@ -12,27 +12,213 @@ extern crate regex;
 extern crate reqwest;
 
 use crate::genedbot::*;
+use crate::orthologs::OrthologTarget;
+use crate::protection::ProtectionRule;
+use crate::retrieved::RetrievedPolicy;
+use crate::sequence_region::SequenceTypeRule;
 use clap::{App, Arg};
 use config::{Config, File};
+use std::collections::HashMap;
 use std::error::Error;
 
+pub mod assembly;
 pub mod evidence;
 pub mod gene;
 pub mod genedbot;
+pub mod go_ontology;
+pub mod gpad;
 pub mod literature;
 pub mod loader;
 pub mod orthologs;
+pub mod protection;
 pub mod protein;
+pub mod proteome;
+pub mod retrieved;
+pub mod sequence_region;
+pub mod structure;
+pub mod transcript;
+
+/// Applies optional bot settings from the config file
+fn apply_settings(bot: &mut GeneDBot, settings: &Config) {
+    match settings.get_bool("bot.symmetric_orthologs") {
+        Ok(b) => bot.orthologs.symmetric = b,
+        _ => {}
+    }
+    match settings.get_bool("bot.report_one_directional_orthologs") {
+        Ok(b) => bot.orthologs.report_one_directional = b,
+        _ => {}
+    }
+    match settings.get_int("bot.ortholog_chunk_size") {
+        Ok(i) => bot.orthologs.chunk_size = i as usize,
+        _ => {}
+    }
+    match settings.get_int("bot.ortholog_max_retries") {
+        Ok(i) => bot.orthologs.max_retries = i as usize,
+        _ => {}
+    }
+    match settings.get_str("bot.ortholog_snapshot") {
+        Ok(s) => bot.orthologs.snapshot_file = Some(s),
+        _ => {}
+    }
+    match settings.get_bool("bot.paralogs") {
+        Ok(b) => bot.orthologs.process_paralogs = b,
+        _ => {}
+    }
+    match settings.get_str("bot.paralog_property") {
+        Ok(s) => bot.orthologs.paralog_property = s,
+        _ => {}
+    }
+    match settings.get_str("bot.ortholog_cluster_qualifier") {
+        Ok(s) => bot.orthologs.cluster_qualifier = Some(s),
+        _ => {}
+    }
+    match settings.get_str("bot.curated_ortholog_method") {
+        Ok(s) => bot.orthologs.set_curated_method(&s),
+        _ => {}
+    }
+    match settings.get_str("bot.gpad") {
+        Ok(s) => bot.gpad_file = Some(s),
+        _ => {}
+    }
+    match settings.get_str("bot.gpi") {
+        Ok(s) => bot.gpi_file = Some(s),
+        _ => {}
+    }
+    match settings.get_bool("bot.gaf_date_as_retrieved") {
+        Ok(b) => bot.gaf_date_as_retrieved = b,
+        _ => {}
+    }
+    match settings.get_str("bot.go_slim") {
+        Ok(s) => bot.go_slim_file = Some(s),
+        _ => {}
+    }
+    match settings.get_str("bot.go_rollup") {
+        Ok(s) => match GoRollup::new_from_str(&s) {
+            Some(rollup) => bot.go_rollup = rollup,
+            None => println!("Unknown go_rollup '{}', using default", &s),
+        },
+        _ => {}
+    }
+    match settings.get_bool("bot.gene_structure") {
+        Ok(b) => bot.gene_structure = b,
+        _ => {}
+    }
+    match settings.get_str("bot.protein_fasta") {
+        Ok(s) => bot.protein_fasta_file = Some(s),
+        _ => {}
+    }
+    match settings.get_table("assembly") {
+        Ok(table) => table
+            .into_iter()
+            .for_each(|(key, value)| match value.into_str() {
+                Ok(value) => {
+                    bot.assembly_overrides.insert(key.to_lowercase(), value);
+                }
+                _ => {}
+            }),
+        _ => {}
+    }
+    match settings.get_table("gaf_taxa") {
+        Ok(table) => table
+            .into_iter()
+            .for_each(|(key, value)| match value.into_str() {
+                Ok(value) => {
+                    let taxa = value
+                        .split(',')
+                        .map(|t| t.trim().trim_start_matches("taxon:").to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect();
+                    bot.gaf_taxa.insert(key.to_lowercase(), taxa);
+                }
+                _ => {}
+            }),
+        _ => {}
+    }
+    match settings.get_str("bot.sequence_types") {
+        Ok(s) => {
+            let (rules, errors) = SequenceTypeRule::new_list_from_str(&s);
+            errors
+                .iter()
+                .for_each(|rule| println!("Ignoring malformed sequence type rule '{}'", rule));
+            bot.sequence_type_rules = rules;
+        }
+        _ => {}
+    }
+    match settings.get_bool("bot.transcript_items") {
+        Ok(b) => bot.transcript_items = b,
+        _ => {}
+    }
+    match settings.get_str("bot.languages") {
+        Ok(s) => {
+            bot.extra_languages = s
+                .split(',')
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty() && l != "en")
+                .collect()
+        }
+        _ => {}
+    }
+    for language in bot.languages() {
+        let section = match language.as_str() {
+            "en" => "descriptions".to_string(),
+            other => format!("descriptions_{}", other),
+        };
+        match settings.get_table(&section) {
+            Ok(table) => table
+                .into_iter()
+                .for_each(|(key, value)| match value.into_str() {
+                    Ok(template) => {
+                        bot.description_templates
+                            .entry(language.clone())
+                            .or_insert(HashMap::new())
+                            .insert(key.to_lowercase(), template);
+                    }
+                    _ => {}
+                }),
+            _ => {}
+        }
+    }
+    match settings.get_str("bot.retrieved_policy") {
+        Ok(s) => match RetrievedPolicy::new_from_str(&s) {
+            Some(policy) => bot.retrieved_policy = policy,
+            None => println!("Unknown retrieved_policy '{}', using default", &s),
+        },
+        _ => {}
+    }
+    match settings.get_str("bot.protection_rules") {
+        Ok(s) => {
+            let (rules, errors) = ProtectionRule::new_list_from_str(&s);
+            errors
+                .iter()
+                .for_each(|rule| println!("Ignoring malformed protection rule '{}'", rule));
+            bot.protection_rules = rules;
+        }
+        _ => {}
+    }
+    match settings.get_str("bot.go_ontology") {
+        Ok(s) => bot.go_ontology_file = Some(s),
+        _ => {}
+    }
+    match settings.get_str("bot.ortholog_target") {
+        Ok(s) => match OrthologTarget::new_from_str(&s) {
+            Some(target) => bot.orthologs.target = target,
+            None => println!("Unknown ortholog_target '{}', using default", &s),
+        },
+        _ => {}
+    }
+}
 
 fn run_bot_for_species_and_gene(
     species_key: &String,
     genes: &Option<Vec<String>>,
     lgname: &str,
     lgpass: &str,
+    settings: &Config,
 ) -> Result<(), Box<dyn Error>> {
     let mut bot = GeneDBot::new();
     //bot.simulate = true;
     bot.verbose = true;
+    apply_settings(&mut bot, settings);
     bot.api().write().unwrap().set_user_agent("GeneDBot/3.0");
     bot.api().write().unwrap().set_edit_delay(Some(500)); // Half a second between edits
     bot.specific_genes_only = genes.to_owned();
@@ -103,7 +289,8 @@ fn main() {
                 let conf = GeneDBotConfig::new_from_json(species);
                 let species_key = conf.abbreviation;
                 println!("> {}", &species_key);
-                match run_bot_for_species_and_gene(&species_key, &None, &lgname, &lgpass) {
+                match run_bot_for_species_and_gene(&species_key, &None, &lgname, &lgpass, &settings)
+                {
                     Ok(_) => {}
                     Err(e) => println!("RUN FAILED: {:?}", e),
                 }
@@ -114,7 +301,8 @@ fn main() {
             Some(gene) => Some(vec![gene.to_string()]),
             None => None,
         };
-        run_bot_for_species_and_gene(&species_key.to_string(), &gene, &lgname, &lgpass).unwrap();
+        run_bot_for_species_and_gene(&species_key.to_string(), &gene, &lgname, &lgpass, &settings)
+            .unwrap();
     }
 }
 
@@ -1,14 +1,64 @@
+use crate::genedbot::GENEDB_ID_ITEM_CLASSES;
 use crate::Toolbox;
+use libflate::gzip::Decoder;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::{thread, time};
 use wikibase::*;
 
-const MIN_LARGE_GROUP: usize = 100;
+const DEFAULT_CHUNK_SIZE: usize = 100;
+const DEFAULT_MAX_RETRIES: usize = 3;
+const RETRY_DELAY_SECONDS: u64 = 5;
+const ORTHOLOG_KEY: &str = "orthologous_to";
+const PARALOG_KEY: &str = "paralogous_to";
+pub const DEFAULT_PARALOG_PROPERTY: &str = "P1796";
+const CURATED_METHOD_Q: &str = "Q23190856"; // inferred by curator
+
+/// A relation to another gene, as parsed from a GFF attribute like `orthologous_to`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneRelation {
+    pub species: String,
+    pub genedb_id: String,
+    pub protein_genedb_id: Option<String>,
+    pub attributes: HashMap<String, String>,
+}
+
+/// Which items get ortholog statements
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrthologTarget {
+    Gene,
+    Protein,
+    Both,
+}
+
+impl OrthologTarget {
+    pub fn new_from_str(s: &str) -> Option<Self> {
+        match s {
+            "gene" => Some(OrthologTarget::Gene),
+            "protein" => Some(OrthologTarget::Protein),
+            "both" => Some(OrthologTarget::Both),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Orthologs {
     pub genedb2q: HashMap<String, String>,
     pub genedb2taxon_q: HashMap<String, String>,
+    pub symmetric: bool,
+    pub report_one_directional: bool,
+    pub chunk_size: usize,
+    pub max_retries: usize,
+    pub snapshot_file: Option<String>,
+    pub process_paralogs: bool,
+    pub paralog_property: String,
+    pub cluster_qualifier: Option<String>,
+    pub target: OrthologTarget,
+    pub program2q: HashMap<String, String>,
+    pub curated_method_q: Option<String>,
 }
 
 impl Toolbox for Orthologs {}
@@ -18,35 +68,107 @@ impl Orthologs {
         Self {
             genedb2q: HashMap::new(),
             genedb2taxon_q: HashMap::new(),
+            symmetric: false,
+            report_one_directional: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_retries: DEFAULT_MAX_RETRIES,
+            snapshot_file: None,
+            process_paralogs: false,
+            paralog_property: DEFAULT_PARALOG_PROPERTY.to_string(),
+            cluster_qualifier: None,
+            target: OrthologTarget::Gene,
+            program2q: HashMap::new(),
+            curated_method_q: None,
         }
     }
 
     // Returns (species,genedb_id)
     pub fn get_from_gff_element(&self, gff: &bio::io::gff::Record) -> Vec<(String, String)> {
+        self.get_relations_from_gff_element(gff, ORTHOLOG_KEY)
+            .into_iter()
+            .map(|r| (r.species, r.genedb_id))
+            .collect()
+    }
+
+    pub fn on_genes(&self) -> bool {
+        self.target != OrthologTarget::Protein
+    }
+
+    pub fn on_proteins(&self) -> bool {
+        self.target != OrthologTarget::Gene
+    }
+
+    /// Returns all GeneDB IDs of orthologs (and paralogs, if used) that need to be resolved to items
+    pub fn get_ids_to_load_from_gff_element(&self, gff: &bio::io::gff::Record) -> Vec<String> {
+        let mut relations = self.get_relations_from_gff_element(gff, ORTHOLOG_KEY);
+        if self.process_paralogs {
+            relations.append(&mut self.get_relations_from_gff_element(gff, PARALOG_KEY));
+        }
+        let mut ret: Vec<String> = vec![];
+        for relation in relations {
+            if self.on_genes() {
+                ret.push(relation.genedb_id);
+            }
+            if self.on_proteins() {
+                match relation.protein_genedb_id {
+                    Some(id) => ret.push(id),
+                    None => {}
+                }
+            }
+        }
+        ret
+    }
+
+    /// Parses a GFF relation attribute (eg orthologous_to, paralogous_to).
+    /// Group attributes following the members (eg cluster_name, program, rank) are attached to every member.
+    pub fn get_relations_from_gff_element(
+        &self,
+        gff: &bio::io::gff::Record,
+        key: &str,
+    ) -> Vec<GeneRelation> {
         lazy_static! {
             static ref RE_ORTH: Regex =
                 Regex::new(r"^\s*(\S*):(\S+)").expect("RE_ORTH does not compile");
+            static ref RE_LINK: Regex =
+                Regex::new(r"\blink=([^\s:]+)").expect("RE_LINK does not compile");
         }
 
-        match gff.attributes().get_vec("orthologous_to") {
-            Some(orths) => {
-                let mut ret: Vec<(String, String)> = vec![];
-                for orth in orths {
-                    let orth = self.fix_attribute_value(orth);
-                    let v: Vec<&str> = orth.split("type=orthologous_to,").collect();
-                    let mut result: Vec<(String, String)> = v
-                        .iter()
-                        .filter(|o| RE_ORTH.is_match(o))
-                        .map(|o| {
-                            RE_ORTH
-                                .captures_iter(o)
-                                .map(|m| return (m[1].to_string(), m[2].to_string()))
-                                .next()
-                        })
-                        .filter(|o| o.is_some())
-                        .map(|o| o.expect("get_orthologs_from_gff_element: [1]"))
-                        .collect();
-                    ret.append(&mut result);
+        let separator = format!("type={},", key);
+        match gff.attributes().get_vec(key) {
+            Some(values) => {
+                let mut ret: Vec<GeneRelation> = vec![];
+                for value in values {
+                    let value = self.fix_attribute_value(value);
+                    let mut attributes: HashMap<String, String> = HashMap::new();
+                    let mut members: Vec<GeneRelation> = vec![];
+                    for part in value.split(separator.as_str()) {
+                        let mut subparts = part.split(';');
+                        match subparts.next() {
+                            Some(member) => match RE_ORTH.captures(member) {
+                                Some(m) => members.push(GeneRelation {
+                                    species: m[1].to_string(),
+                                    genedb_id: m[2].to_string(),
+                                    protein_genedb_id: RE_LINK
+                                        .captures(member)
+                                        .map(|l| l[1].to_string()),
+                                    attributes: HashMap::new(),
+                                }),
+                                None => {}
+                            },
+                            None => {}
+                        }
+                        subparts.for_each(|kv| {
+                            let kv: Vec<&str> = kv.splitn(2, '=').collect();
+                            if kv.len() == 2 {
+                                attributes
+                                    .insert(kv[0].trim().to_string(), kv[1].trim().to_string());
+                            }
+                        });
+                    }
+                    members.into_iter().for_each(|mut relation| {
+                        relation.attributes = attributes.clone();
+                        ret.push(relation)
+                    });
                 }
                 ret
             }
@@ -54,113 +176,300 @@ impl Orthologs {
         }
     }
 
+    /// Adds P684 claims (and paralog claims, if used) to the gene item, and returns the ortholog items that were linked
     pub fn process(
         &mut self,
         child: &Vec<(String, String)>,
         gff: &HashMap<String, bio::io::gff::Record>,
         item: &mut Entity,
         reference: &Reference,
-    ) {
+    ) -> Vec<String> {
+        self.process_all_relations(false, child, gff, item, reference)
+    }
+
+    /// Adds P684 claims (and paralog claims, if used) to the protein item, pointing to the ortholog protein items.
+    /// Returns the ortholog protein items that were linked.
+    pub fn process_protein(
+        &mut self,
+        protein_genedb_id: &String,
+        gff: &HashMap<String, bio::io::gff::Record>,
+        item: &mut Entity,
+        reference: &Reference,
+    ) -> Vec<String> {
+        let child = match gff.get(protein_genedb_id) {
+            Some(protein) => vec![(
+                protein_genedb_id.to_string(),
+                protein.feature_type().to_string(),
+            )],
+            None => return vec![],
+        };
+        self.process_all_relations(true, &child, gff, item, reference)
+    }
+
+    fn process_all_relations(
+        &self,
+        use_protein_ids: bool,
+        child: &Vec<(String, String)>,
+        gff: &HashMap<String, bio::io::gff::Record>,
+        item: &mut Entity,
+        reference: &Reference,
+    ) -> Vec<String> {
+        let ret = self.process_relations(
+            ORTHOLOG_KEY,
+            "P684",
+            use_protein_ids,
+            child,
+            gff,
+            item,
+            reference,
+        );
+        if self.process_paralogs {
+            self.process_relations(
+                PARALOG_KEY,
+                &self.paralog_property,
+                use_protein_ids,
+                child,
+                gff,
+                item,
+                reference,
+            );
+        }
+        ret
+    }
+
+    fn process_relations(
+        &self,
+        key: &str,
+        property: &str,
+        use_protein_ids: bool,
+        child: &Vec<(String, String)>,
+        gff: &HashMap<String, bio::io::gff::Record>,
+        item: &mut Entity,
+        reference: &Reference,
+    ) -> Vec<String> {
         let mut had_that: HashSet<String> = HashSet::new();
+        let mut added: Vec<String> = vec![];
         child
             .iter()
             .filter(|o| self.is_product_type(&o.1))
             .for_each(|o| match gff.get(&o.0) {
                 Some(protein) => {
-                    self.get_from_gff_element(&protein).iter().for_each(
-                        |(_species, protein_genedb_id)| match self.genedb2q.get(protein_genedb_id) {
-                            Some(orth_q) => {
-                                if had_that.contains(orth_q) {
-                                    return;
+                    self.get_relations_from_gff_element(&protein, key)
+                        .iter()
+                        .for_each(|relation| {
+                            let related_genedb_id = if use_protein_ids {
+                                match &relation.protein_genedb_id {
+                                    Some(id) => id,
+                                    None => return,
                                 }
-                                had_that.insert(orth_q.to_string());
-                                match self.genedb2taxon_q.get(protein_genedb_id) {
-                                    Some(orth_q_taxon) => {
-                                        item.add_claim(Statement::new_normal(
-                                            Snak::new_item("P684", orth_q),
-                                            vec![Snak::new_item("P703", orth_q_taxon)],
-                                            vec![reference.clone()],
-                                        ));
-                                    }
-                                    None => {}
+                            } else {
+                                &relation.genedb_id
+                            };
+                            let related_q = match self.genedb2q.get(related_genedb_id) {
+                                Some(q) => q,
+                                None => return,
+                            };
+                            if had_that.contains(related_q) {
+                                return;
+                            }
+                            had_that.insert(related_q.to_string());
+                            match self.genedb2taxon_q.get(related_genedb_id) {
+                                Some(related_q_taxon) => {
+                                    item.add_claim(Statement::new_normal(
+                                        Snak::new_item(property, related_q),
+                                        self.get_relation_qualifiers(relation, related_q_taxon),
+                                        vec![reference.clone()],
+                                    ));
+                                    added.push(related_q.to_string());
                                 }
+                                None => {}
                             }
-                            None => {}
-                        },
-                    );
+                        });
                 }
                 None => {}
             });
+        added
     }
 
-    pub fn load(
-        self: &mut Self,
-        api: &wikibase::mediawiki::api::Api,
-        orth_ids: HashSet<String>,
-    ) -> Result<(), Box<dyn (::std::error::Error)>> {
-        if orth_ids.is_empty() {
-            return Ok(());
+    fn get_relation_qualifiers(&self, relation: &GeneRelation, taxon_q: &String) -> Vec<Snak> {
+        let mut qualifiers = vec![Snak::new_item("P703", taxon_q)];
+        match (
+            &self.cluster_qualifier,
+            relation.attributes.get("cluster_name"),
+        ) {
+            (Some(prop), Some(cluster_name)) => {
+                qualifiers.push(Snak::new_string(prop.as_str(), cluster_name.as_str()))
+            }
+            _ => {}
+        }
+        match self.get_method_q(relation) {
+            Some(method_q) => qualifiers.push(Snak::new_item("P459", &method_q)),
+            None => {}
         }
+        qualifiers
+    }
 
-        if orth_ids.len() < MIN_LARGE_GROUP {
-            self.load_small_group(api, orth_ids)
-        } else {
-            self.load_large_group(api, orth_ids)
+    /// Returns the determination method item; computed orthologs have a program, curated ones do not
+    fn get_method_q(&self, relation: &GeneRelation) -> Option<String> {
+        match relation.attributes.get("program") {
+            Some(program) => self.program2q.get(program).map(|q| q.to_string()),
+            None => self.curated_method_q.to_owned(),
         }
     }
 
-    fn load_small_group(
+    /// Returns all ortholog (and paralog, if used) programs used in a GFF element
+    pub fn get_programs_from_gff_element(&self, gff: &bio::io::gff::Record) -> Vec<String> {
+        let mut relations = self.get_relations_from_gff_element(gff, ORTHOLOG_KEY);
+        if self.process_paralogs {
+            relations.append(&mut self.get_relations_from_gff_element(gff, PARALOG_KEY));
+        }
+        relations
+            .iter()
+            .filter_map(|relation| relation.attributes.get("program"))
+            .map(|program| program.to_string())
+            .collect()
+    }
+
+    /// Sets the P459 qualifier item for curated orthologs; "curator" for inferred by curator
+    pub fn set_curated_method(&mut self, s: &str) {
+        self.curated_method_q = match s.trim() {
+            "curator" => Some(CURATED_METHOD_Q.to_string()),
+            q => Some(q.to_string()),
+        };
+    }
+
+    /// Finds software items for ortholog programs, by English label. Returns the programs without an item.
+    pub fn load_programs(
         self: &mut Self,
         api: &wikibase::mediawiki::api::Api,
-        mut orth_ids: HashSet<String>,
-    ) -> Result<(), Box<dyn (::std::error::Error)>> {
-        // Usually for testing
-        let orth_ids: Vec<String> = orth_ids.drain().collect();
-        for chunk in orth_ids.chunks(100) {
-            let sparql = format!("SELECT ?q ?genedb ?taxon {{ VALUES ?genedb {{'{}'}} . ?q wdt:P3382 ?genedb ; wdt:P703 ?taxon }}",chunk.join("' '"));
-            let sparql_result = api.sparql_query(&sparql)?;
-            for b in sparql_result["results"]["bindings"].as_array().unwrap() {
+        programs: HashSet<String>,
+    ) -> Result<Vec<String>, Box<dyn (::std::error::Error)>> {
+        let mut programs: Vec<String> = programs
+            .into_iter()
+            .filter(|program| !self.program2q.contains_key(program))
+            .filter(|program| !program.contains('\''))
+            .collect();
+        programs.sort();
+        if !programs.is_empty() {
+            let sparql = format!("SELECT ?q ?label {{ VALUES ?label {{'{}'@en}} . ?q rdfs:label ?label ; wdt:P31/wdt:P279* wd:Q7397 }}",programs.join("'@en '"));
+            // Programs are optional; on failure, all are returned as unknown
+            let sparql_result = match self.sparql_query_with_retries(api, &sparql) {
+                Ok(result) => result,
+                Err(e) => {
+                    println!("Could not load ortholog programs: {}", e);
+                    serde_json::Value::Null
+                }
+            };
+            let bindings = match sparql_result["results"]["bindings"].as_array() {
+                Some(bindings) => bindings.to_owned(),
+                None => vec![],
+            };
+            for b in bindings {
                 let q = match b["q"]["value"].as_str() {
-                    Some(s) => api.extract_entity_from_uri(s).unwrap(),
+                    Some(s) => match api.extract_entity_from_uri(s) {
+                        Ok(q) => q,
+                        Err(_) => continue,
+                    },
                     None => continue,
                 };
-                let taxon_q = match b["taxon"]["value"].as_str() {
-                    Some(s) => api.extract_entity_from_uri(s).unwrap(),
-                    None => continue,
-                };
-                let genedb = match b["genedb"]["value"].as_str() {
+                let label = match b["label"]["value"].as_str() {
                     Some(s) => s.to_string(),
                     None => continue,
                 };
-                self.genedb2q.insert(genedb.to_string(), q);
-                self.genedb2taxon_q.insert(genedb.to_string(), taxon_q);
+                // Multiple, use first one
+                if !self.program2q.contains_key(&label) {
+                    self.program2q.insert(label, q);
+                }
             }
         }
-        Ok(())
+        Ok(programs
+            .into_iter()
+            .filter(|program| !self.program2q.contains_key(program))
+            .collect())
     }
 
-    fn load_large_group(
+    /// Checks if syncing the reverse ortholog links is required at all
+    pub fn check_reverse(&self) -> bool {
+        self.symmetric || self.report_one_directional
+    }
+
+    pub fn load(
         self: &mut Self,
         api: &wikibase::mediawiki::api::Api,
         orth_ids: HashSet<String>,
     ) -> Result<(), Box<dyn (::std::error::Error)>> {
-        // Retrieven 'em all and let HashSet sort 'em out...
-        let sparql = "SELECT ?q ?genedb ?taxon { ?q wdt:P3382 ?genedb ; wdt:P703 ?taxon }";
-        let sparql_result = api.sparql_query(&sparql)?;
-        for b in sparql_result["results"]["bindings"].as_array().unwrap() {
-            let genedb = match b["genedb"]["value"].as_str() {
-                Some(s) => s.to_string(),
-                None => continue,
-            };
-            if !orth_ids.contains(&genedb) {
+        if orth_ids.is_empty() {
+            return Ok(());
+        }
+
+        let orth_ids = match self.snapshot_file.clone() {
+            Some(filename) => self.load_from_snapshot(api, &filename, orth_ids)?,
+            None => orth_ids,
+        };
+
+        let mut orth_ids: Vec<String> = orth_ids.into_iter().collect();
+        orth_ids.sort();
+        let chunk_size = std::cmp::max(1, self.chunk_size);
+        for chunk in orth_ids.chunks(chunk_size) {
+            self.load_chunk(api, chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Loads the orthologs from a local snapshot file (tab-separated GeneDB ID, item, taxon item; can be gzipped).
+    /// Returns the IDs that were not found in the snapshot.
+    fn load_from_snapshot(
+        self: &mut Self,
+        api: &wikibase::mediawiki::api::Api,
+        filename: &String,
+        mut orth_ids: HashSet<String>,
+    ) -> Result<HashSet<String>, Box<dyn (::std::error::Error)>> {
+        let file = File::open(filename)?;
+        let reader: Box<dyn BufRead> = if filename.ends_with(".gz") {
+            Box::new(BufReader::new(Decoder::new(file)?))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+        for line in reader.lines() {
+            let line = line?;
+            let parts: Vec<&str> = line.split('\t').map(|s| s.trim()).collect();
+            if parts.len() < 3 || !orth_ids.contains(parts[0]) {
                 continue;
             }
+            let q = api
+                .extract_entity_from_uri(parts[1])
+                .unwrap_or(parts[1].to_string());
+            let taxon_q = api
+                .extract_entity_from_uri(parts[2])
+                .unwrap_or(parts[2].to_string());
+            self.genedb2q.insert(parts[0].to_string(), q);
+            self.genedb2taxon_q.insert(parts[0].to_string(), taxon_q);
+        }
+        orth_ids.retain(|id| !self.genedb2q.contains_key(id));
+        Ok(orth_ids)
+    }
+
+    fn load_chunk(
+        self: &mut Self,
+        api: &wikibase::mediawiki::api::Api,
+        chunk: &[String],
+    ) -> Result<(), Box<dyn (::std::error::Error)>> {
+        let sparql = format!("SELECT ?q ?genedb ?taxon {{ VALUES ?genedb {{'{}'}} . VALUES ?class {{ wd:{} }} . ?q wdt:P3382 ?genedb ; wdt:P703 ?taxon ; wdt:P31/wdt:P279* ?class }}",chunk.join("' '"),GENEDB_ID_ITEM_CLASSES.join(" wd:"));
+        let sparql_result = self.sparql_query_with_retries(api, &sparql)?;
+        let bindings = sparql_result["results"]["bindings"]
+            .as_array()
+            .ok_or("load_chunk: SPARQL result has no bindings")?;
+        for b in bindings {
             let q = match b["q"]["value"].as_str() {
-                Some(s) => api.extract_entity_from_uri(s).unwrap(),
+                Some(s) => api.extract_entity_from_uri(s)?,
                 None => continue,
             };
             let taxon_q = match b["taxon"]["value"].as_str() {
-                Some(s) => api.extract_entity_from_uri(s).unwrap(),
+                Some(s) => api.extract_entity_from_uri(s)?,
+                None => continue,
+            };
+            let genedb = match b["genedb"]["value"].as_str() {
+                Some(s) => s.to_string(),
                 None => continue,
             };
             self.genedb2q.insert(genedb.to_string(), q);
@@ -168,6 +477,41 @@ impl Orthologs {
         }
         Ok(())
     }
+
+    fn sparql_query_with_retries(
+        &self,
+        api: &wikibase::mediawiki::api::Api,
+        sparql: &str,
+    ) -> Result<serde_json::Value, Box<dyn (::std::error::Error)>> {
+        let mut attempt: u64 = 0;
+        loop {
+            // Error responses can come back as JSON without results; retry those as well
+            let result = match api.sparql_query(sparql) {
+                Ok(result) => {
+                    if result["results"]["bindings"].is_array() {
+                        Ok(result)
+                    } else {
+                        Err(format!("Malformed SPARQL result: {}", result))
+                    }
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(result) => return Ok(result),
+                Err(e) => {
+                    if attempt >= self.max_retries as u64 {
+                        return Err(From::from(format!(
+                            "SPARQL query failed after {} attempts: {}",
+                            attempt + 1,
+                            e
+                        )));
+                    }
+                }
+            }
+            attempt += 1;
+            thread::sleep(time::Duration::from_secs(RETRY_DELAY_SECONDS * attempt));
+        }
+    }
 }
 
 #[cfg(test)]
@@ -180,6 +524,7 @@ mod tests {
         let o = Orthologs::new();
         assert_eq!(o.genedb2q.len(), 0);
         assert_eq!(o.genedb2taxon_q.len(), 0);
+        assert!(!o.check_reverse());
     }
 
     #[test]
@@ -194,6 +539,103 @@ mod tests {
             .any(|r| r.0 == "Preichenowi" && r.1 == "PRCDC_0042600"));
     }
 
+    #[test]
+    fn test_get_relations_from_gff_element() {
+        let o = Orthologs::new();
+        let mut gff_element = bio::io::gff::Record::new();
+        gff_element.attributes_mut().insert(
+            "paralogous_to".to_string(),
+            "Pfalciparum:PF3D7_0100100 link%3DPF3D7_0100100.1:pep type%3Dparalogous_to%2C Pfalciparum:PF3D7_0200100 link%3DPF3D7_0200100.1:pep type%3Dparalogous_to%3B cluster_name%3DPlasmodium:ORTHOMCL5%3B program%3DOrthoMCL%3B rank%3D0".to_string(),
+        );
+        let result = o.get_relations_from_gff_element(&gff_element, "paralogous_to");
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].species, "Pfalciparum");
+        assert_eq!(result[0].genedb_id, "PF3D7_0100100");
+        assert_eq!(result[1].genedb_id, "PF3D7_0200100");
+        assert_eq!(
+            result[1].protein_genedb_id,
+            Some("PF3D7_0200100.1".to_string())
+        );
+        assert_eq!(
+            result[1].attributes.get("cluster_name"),
+            Some(&"Plasmodium:ORTHOMCL5".to_string())
+        );
+        assert_eq!(
+            result[0].attributes.get("program"),
+            Some(&"OrthoMCL".to_string())
+        );
+        assert!(o.get_from_gff_element(&gff_element).is_empty());
+        assert!(o.get_ids_to_load_from_gff_element(&gff_element).is_empty());
+    }
+
+    #[test]
+    fn test_get_relation_qualifiers() {
+        let mut o = Orthologs::new();
+        o.program2q
+            .insert("OrthoMCL".to_string(), "Q123".to_string());
+        let mut relation = GeneRelation {
+            species: "Pfalciparum".to_string(),
+            genedb_id: "PF3D7_0100100".to_string(),
+            protein_genedb_id: None,
+            attributes: HashMap::new(),
+        };
+        let taxon_q = "Q456".to_string();
+        assert_eq!(
+            o.get_relation_qualifiers(&relation, &taxon_q),
+            vec![Snak::new_item("P703", "Q456")]
+        );
+        o.set_curated_method("curator");
+        assert_eq!(
+            o.get_relation_qualifiers(&relation, &taxon_q),
+            vec![
+                Snak::new_item("P703", "Q456"),
+                Snak::new_item("P459", CURATED_METHOD_Q),
+            ]
+        );
+        relation
+            .attributes
+            .insert("program".to_string(), "OrthoMCL".to_string());
+        assert_eq!(
+            o.get_relation_qualifiers(&relation, &taxon_q),
+            vec![
+                Snak::new_item("P703", "Q456"),
+                Snak::new_item("P459", "Q123"),
+            ]
+        );
+        relation
+            .attributes
+            .insert("program".to_string(), "unknown program".to_string());
+        assert_eq!(
+            o.get_relation_qualifiers(&relation, &taxon_q),
+            vec![Snak::new_item("P703", "Q456")]
+        );
+    }
+
+    #[test]
+    fn test_get_ids_to_load_from_gff_element() {
+        let mut o = Orthologs::new();
+        let mut gff_element = bio::io::gff::Record::new();
+        gff_element.attributes_mut().insert(
+            "orthologous_to".to_string(),
+            "Preichenowi:PRCDC_0042600 link%3DPRCDC_0042600.1:pep type%3Dorthologous_to"
+                .to_string(),
+        );
+        assert_eq!(
+            o.get_ids_to_load_from_gff_element(&gff_element),
+            vec!["PRCDC_0042600".to_string()]
+        );
+        o.target = OrthologTarget::Both;
+        assert_eq!(
+            o.get_ids_to_load_from_gff_element(&gff_element),
+            vec!["PRCDC_0042600".to_string(), "PRCDC_0042600.1".to_string()]
+        );
+        o.target = OrthologTarget::Protein;
+        assert_eq!(
+            o.get_ids_to_load_from_gff_element(&gff_element),
+            vec!["PRCDC_0042600.1".to_string()]
+        );
+    }
+
     #[test]
     fn test_process() {
         let mut o = Orthologs::new();
@@ -206,7 +648,8 @@ mod tests {
         let child = vec![("PF3D7_0100200.1".to_string(), "mRNA".to_string())];
         let mut item = Entity::new_empty_item();
         let reference = Reference::new(vec![Snak::new_string("P214", "test")]);
-        let _result = o.process(&child, &bot.gff, &mut item, &reference);
+        let result = o.process(&child, &bot.gff, &mut item, &reference);
+        assert_eq!(result, vec!["Q123".to_string()]);
         assert!(item.has_target_entity("P684", "Q123"));
         assert_eq!(
             *item.claims().get(0).unwrap().qualifiers().get(0).unwrap(),
@@ -248,7 +691,7 @@ mod tests {
             wikibase::mediawiki::api::Api::new("https://www.wikidata.org/w/api.php").unwrap();
         let mut orth_ids: HashSet<String> = HashSet::new();
         orth_ids.insert("PF3D7_0102600".to_string());
-        for x in 0..MIN_LARGE_GROUP + 5 {
+        for x in 0..DEFAULT_CHUNK_SIZE + 5 {
             orth_ids.insert("this does not exist".to_string() + &format!("{}", x));
         }
         o.load(&mut api, orth_ids).unwrap();
@@ -257,4 +700,34 @@ mod tests {
         assert_eq!(o.genedb2q.get("PF3D7_0102600").unwrap(), "Q18968367");
         assert_eq!(o.genedb2taxon_q.get("PF3D7_0102600").unwrap(), "Q61779043");
     }
+
+    #[test]
+    fn test_load_from_snapshot() {
+        let filename = std::env::temp_dir().join(format!(
+            "genedbot_orthologs_snapshot_{}_{}.tsv",
+            std::process::id(),
+            time::SystemTime::now()
+                .duration_since(time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        std::fs::write(
+            &filename,
+            "PF3D7_0102600\thttp://www.wikidata.org/entity/Q18968367\tQ61779043\nPF3D7_0000000\tQ1\tQ2\n",
+        )
+        .unwrap();
+        let mut o = Orthologs::new();
+        let api = wikibase::mediawiki::api::Api::new("https://www.wikidata.org/w/api.php").unwrap();
+        let mut orth_ids: HashSet<String> = HashSet::new();
+        orth_ids.insert("PF3D7_0102600".to_string());
+        orth_ids.insert("not in snapshot".to_string());
+        let filename = filename.to_str().unwrap().to_string();
+        let missing = o.load_from_snapshot(&api, &filename, orth_ids).unwrap();
+        std::fs::remove_file(&filename).unwrap();
+        assert_eq!(missing.len(), 1);
+        assert!(missing.contains("not in snapshot"));
+        assert_eq!(o.genedb2q.len(), 1);
+        assert_eq!(o.genedb2q.get("PF3D7_0102600").unwrap(), "Q18968367");
+        assert_eq!(o.genedb2taxon_q.get("PF3D7_0102600").unwrap(), "Q61779043");
+    }
 }
@@ -0,0 +1,331 @@
+use std::collections::HashSet;
+use wikibase::entity_diff::*;
+use wikibase::*;
+
+const GENEDB_Q: &str = "Q5531047";
+
+pub const DEFAULT_PROTECTION_RULES: &str = "protein|P680,P681,P682,P1343|multiple_references;protein|P680,P681,P682,P1343|foreign_curator;gene|P279,P684|not_from_bot;gene,protein|P684|reference_property:P3452;chromosome|P31,P361,P973,P2249|not_from_bot";
+
+/// The kind of item a diff is for
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemKind {
+    Gene,
+    Protein,
+    Transcript,
+    Chromosome,
+    Assembly,
+}
+
+impl ItemKind {
+    pub fn new_from_str(s: &str) -> Option<Self> {
+        match s {
+            "gene" => Some(ItemKind::Gene),
+            "protein" => Some(ItemKind::Protein),
+            "transcript" => Some(ItemKind::Transcript),
+            "chromosome" => Some(ItemKind::Chromosome),
+            "assembly" => Some(ItemKind::Assembly),
+            _ => None,
+        }
+    }
+}
+
+/// When a claim is protected from removal
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtectionCondition {
+    /// The claim has more than one reference block
+    MultipleReferences,
+    /// A reference with a curator (P1640) was not made by the bot
+    ForeignCurator,
+    /// No reference was made by the bot
+    NotFromBot,
+    /// A reference uses the given property (eg P143 for "imported from")
+    ReferenceProperty(String),
+    /// The item was last edited by a human (not a bot account)
+    HumanEditor,
+}
+
+impl ProtectionCondition {
+    pub fn new_from_str(s: &str) -> Option<Self> {
+        let mut parts = s.trim().splitn(2, ':');
+        match (parts.next()?, parts.next()) {
+            ("multiple_references", None) => Some(ProtectionCondition::MultipleReferences),
+            ("foreign_curator", None) => Some(ProtectionCondition::ForeignCurator),
+            ("not_from_bot", None) => Some(ProtectionCondition::NotFromBot),
+            ("reference_property", Some(p)) => {
+                Some(ProtectionCondition::ReferenceProperty(p.trim().to_string()))
+            }
+            ("human_editor", None) => Some(ProtectionCondition::HumanEditor),
+            _ => None,
+        }
+    }
+}
+
+/// What the bot knows about the item and its own references
+#[derive(Debug, Clone)]
+pub struct ProtectionContext {
+    /// Curators the bot uses in its GO annotation references
+    pub own_curators: HashSet<String>,
+    pub last_editor_is_human: bool,
+}
+
+/// A rule protecting claims of certain properties, on certain kinds of items, from removal by the bot
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtectionRule {
+    /// Empty for all kinds of items
+    pub item_kinds: Vec<ItemKind>,
+    /// Empty for all properties
+    pub properties: Vec<String>,
+    pub condition: ProtectionCondition,
+}
+
+impl ProtectionRule {
+    /// Parses "KINDS|PROPERTIES|CONDITION", eg "gene,protein|P279,P684|not_from_bot"; "*" for all kinds or properties
+    pub fn new_from_str(s: &str) -> Option<Self> {
+        let parts: Vec<&str> = s.trim().split('|').collect();
+        if parts.len() != 3 {
+            return None;
+        }
+        let mut item_kinds = vec![];
+        for kind in parts[0].split(',').map(|k| k.trim()) {
+            match kind {
+                "*" => {}
+                kind => item_kinds.push(ItemKind::new_from_str(kind)?),
+            }
+        }
+        let properties = parts[1]
+            .split(',')
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty() && *p != "*")
+            .map(|p| p.to_string())
+            .collect();
+        Some(Self {
+            item_kinds,
+            properties,
+            condition: ProtectionCondition::new_from_str(parts[2])?,
+        })
+    }
+
+    /// Parses rules separated by ";"; returns the rules, and the rule strings that could not be parsed
+    pub fn new_list_from_str(s: &str) -> (Vec<Self>, Vec<String>) {
+        let mut rules = vec![];
+        let mut errors = vec![];
+        for rule in s.split(';').filter(|r| !r.trim().is_empty()) {
+            match ProtectionRule::new_from_str(rule) {
+                Some(r) => rules.push(r),
+                None => errors.push(rule.trim().to_string()),
+            }
+        }
+        (rules, errors)
+    }
+
+    pub fn applies_to(&self, kind: &ItemKind, property: &str) -> bool {
+        (self.item_kinds.is_empty() || self.item_kinds.contains(kind))
+            && (self.properties.is_empty() || self.properties.iter().any(|p| p == property))
+    }
+
+    /// Checks if the rule protects the claim from removal
+    pub fn protects(&self, claim: &Statement, context: &ProtectionContext) -> bool {
+        let references = claim.references();
+        match &self.condition {
+            ProtectionCondition::MultipleReferences => references.len() > 1,
+            ProtectionCondition::ForeignCurator => references.iter().any(|reference| {
+                reference
+                    .snaks()
+                    .iter()
+                    .any(|snak| snak.property() == "P1640")
+                    && !is_own_reference(reference, context)
+            }),
+            ProtectionCondition::NotFromBot => !references
+                .iter()
+                .any(|reference| is_own_reference(reference, context)),
+            ProtectionCondition::ReferenceProperty(property) => references.iter().any(|r| {
+                r.snaks()
+                    .iter()
+                    .any(|snak| snak.property() == property.as_str())
+            }),
+            ProtectionCondition::HumanEditor => context.last_editor_is_human,
+        }
+    }
+}
+
+fn get_item_value(snak: &Snak) -> Option<String> {
+    match snak.data_value() {
+        Some(dv) => match dv.value() {
+            Value::Entity(value) => Some(value.id().to_string()),
+            _ => None,
+        },
+        None => None,
+    }
+}
+
+/// Reference made by the bot: "stated in GeneDB", GeneDB as curator, or another curator used by the bot
+/// together with the GeneDB release version (P348). A curator alone does not make a reference the bot's own,
+/// as third-party GO annotations use the same curators (eg UniProt).
+fn is_own_reference(reference: &Reference, context: &ProtectionContext) -> bool {
+    let snaks = reference.snaks();
+    let has_release = snaks.iter().any(|snak| snak.property() == "P348");
+    snaks.iter().any(|snak| {
+        let q = match get_item_value(snak) {
+            Some(q) => q,
+            None => return false,
+        };
+        match snak.property() {
+            "P248" => q == GENEDB_Q,
+            "P1640" => q == GENEDB_Q || (has_release && context.own_curators.contains(&q)),
+            _ => false,
+        }
+    })
+}
+
+/// Checks if any of the rules needs to know who last edited the item
+pub fn needs_editor(rules: &Vec<ProtectionRule>, kind: &ItemKind) -> bool {
+    rules.iter().any(|rule| {
+        rule.condition == ProtectionCondition::HumanEditor
+            && (rule.item_kinds.is_empty() || rule.item_kinds.contains(kind))
+    })
+}
+
+/// Removes claim removals from the diff that are protected by a rule; returns the protected claim IDs
+pub fn apply_rules(
+    rules: &Vec<ProtectionRule>,
+    kind: &ItemKind,
+    diff: &mut EntityDiff,
+    original_item: &Entity,
+    context: &ProtectionContext,
+) -> Vec<String> {
+    let mut protected = vec![];
+    let actions = diff.actions_mut();
+    if actions["claims"].is_null() {
+        return protected;
+    }
+    let claim_actions = match actions["claims"].as_array_mut() {
+        Some(c) => c,
+        None => return protected,
+    };
+
+    // Closure should return false to remove the action, keeping the statement in the item
+    claim_actions.retain(|action| {
+        // Removals only
+        if !action["remove"].is_string() {
+            return true;
+        }
+        let id = match action["id"].as_str() {
+            Some(id) => id,
+            None => return true,
+        };
+        let claim = match original_item.claim_with_id(id) {
+            Some(claim) => claim,
+            None => return true,
+        };
+        let property = claim.main_snak().property();
+        let is_protected = rules
+            .iter()
+            .filter(|rule| rule.applies_to(kind, property))
+            .any(|rule| rule.protects(claim, context));
+        if is_protected {
+            protected.push(id.to_string());
+        }
+        !is_protected
+    });
+    protected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> ProtectionContext {
+        ProtectionContext {
+            own_curators: vec!["Q5531047".to_string(), "Q905695".to_string()]
+                .into_iter()
+                .collect(),
+            last_editor_is_human: false,
+        }
+    }
+
+    fn claim(references: Vec<Reference>) -> Statement {
+        Statement::new_normal(Snak::new_item("P680", "Q1"), vec![], references)
+    }
+
+    #[test]
+    fn test_rule_new_from_str() {
+        let rule = ProtectionRule::new_from_str("gene,protein|P279, P684|not_from_bot").unwrap();
+        assert_eq!(rule.item_kinds, vec![ItemKind::Gene, ItemKind::Protein]);
+        assert_eq!(rule.properties, vec!["P279", "P684"]);
+        assert_eq!(rule.condition, ProtectionCondition::NotFromBot);
+        let rule = ProtectionRule::new_from_str("*|*|reference_property:P143").unwrap();
+        assert!(rule.item_kinds.is_empty());
+        assert!(rule.properties.is_empty());
+        assert!(rule.applies_to(&ItemKind::Assembly, "P31"));
+        assert_eq!(ProtectionRule::new_from_str("gene|P279"), None);
+        assert_eq!(ProtectionRule::new_from_str("foo|P279|not_from_bot"), None);
+        assert_eq!(ProtectionRule::new_from_str("gene|P279|foo"), None);
+        let (rules, errors) = ProtectionRule::new_list_from_str(DEFAULT_PROTECTION_RULES);
+        assert_eq!(rules.len(), 5);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_protects() {
+        let ctx = context();
+        let genedb = Reference::new(vec![Snak::new_item("P248", "Q5531047")]);
+        let uniprot = Reference::new(vec![
+            Snak::new_item("P1640", "Q905695"),
+            Snak::new_string("P348", "2020-01"),
+        ]);
+        let foreign_uniprot = Reference::new(vec![Snak::new_item("P1640", "Q905695")]);
+        let foreign = Reference::new(vec![Snak::new_item("P1640", "Q42")]);
+        let imported = Reference::new(vec![Snak::new_item("P143", "Q328")]);
+
+        let rule = ProtectionRule::new_from_str("*|*|multiple_references").unwrap();
+        assert!(!rule.protects(&claim(vec![genedb.clone()]), &ctx));
+        assert!(rule.protects(&claim(vec![genedb.clone(), uniprot.clone()]), &ctx));
+
+        let rule = ProtectionRule::new_from_str("*|*|foreign_curator").unwrap();
+        assert!(!rule.protects(&claim(vec![uniprot.clone()]), &ctx));
+        assert!(!rule.protects(&claim(vec![genedb.clone()]), &ctx));
+        assert!(rule.protects(&claim(vec![foreign.clone()]), &ctx));
+        assert!(rule.protects(&claim(vec![foreign_uniprot.clone()]), &ctx));
+
+        let rule = ProtectionRule::new_from_str("*|*|not_from_bot").unwrap();
+        assert!(!rule.protects(&claim(vec![genedb.clone()]), &ctx));
+        assert!(!rule.protects(&claim(vec![uniprot.clone()]), &ctx));
+        assert!(rule.protects(&claim(vec![foreign_uniprot.clone()]), &ctx));
+        assert!(rule.protects(&claim(vec![imported.clone()]), &ctx));
+        assert!(rule.protects(&claim(vec![]), &ctx));
+
+        let rule = ProtectionRule::new_from_str("*|*|reference_property:P143").unwrap();
+        assert!(rule.protects(&claim(vec![imported.clone()]), &ctx));
+        assert!(!rule.protects(&claim(vec![genedb.clone()]), &ctx));
+
+        let rule = ProtectionRule::new_from_str("*|*|human_editor").unwrap();
+        assert!(!rule.protects(&claim(vec![]), &ctx));
+        let mut ctx = ctx;
+        ctx.last_editor_is_human = true;
+        assert!(rule.protects(&claim(vec![]), &ctx));
+    }
+
+    #[test]
+    fn test_foreign_uniprot_go_annotation_survives() {
+        let ctx = context();
+        let (rules, _) = ProtectionRule::new_list_from_str(DEFAULT_PROTECTION_RULES);
+        let is_protected = |claim: &Statement| {
+            rules
+                .iter()
+                .filter(|rule| rule.applies_to(&ItemKind::Protein, "P680"))
+                .any(|rule| rule.protects(claim, &ctx))
+        };
+        // GO annotation imported by another bot, curated by UniProt
+        let foreign = Reference::new(vec![
+            Snak::new_item("P248", "Q2013"),
+            Snak::new_item("P1640", "Q905695"),
+        ]);
+        assert!(is_protected(&claim(vec![foreign])));
+        // The bot's own annotation from a GAF row assigned by UniProt
+        let own = Reference::new(vec![
+            Snak::new_item("P1640", "Q905695"),
+            Snak::new_string("P348", "2020-01"),
+        ]);
+        assert!(!is_protected(&claim(vec![own])));
+    }
+}
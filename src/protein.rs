@@ -1,3 +1,7 @@
+use crate::gene::link_orthologs;
+use crate::protection::ItemKind;
+use crate::proteome;
+use crate::structure;
 use crate::{GeneDBot, Literature, Toolbox};
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
@@ -20,10 +24,7 @@ pub fn process(
         None => Entity::new_empty_item(),
     };
 
-    let reference = Reference::new(vec![
-        Snak::new_item("P248", "Q5531047"),
-        bot.new_time_today(),
-    ]);
+    let reference = bot.genedb_reference(gene_genedb_id);
 
     let mut literature: HashSet<Literature> = HashSet::new();
 
@@ -82,8 +83,26 @@ pub fn process(
 
     add_go_annotation(bot, &mut item, &gff, &mut literature);
 
+    // With transcript items, the gene model goes there instead
+    if bot.gene_structure && !bot.transcript_items {
+        structure::add_structure_statements(bot, protein_genedb_id, &mut item, &reference);
+    }
+
+    if bot.protein_fasta_file.is_some() {
+        proteome::add_sequence_statements(bot, protein_genedb_id, &mut item, &reference);
+    }
+
+    // Orthologs
+    let mut ortholog_entity_ids: Vec<String> = vec![];
+    if bot.orthologs.on_proteins() {
+        ortholog_entity_ids =
+            bot.orthologs
+                .process_protein(&protein_genedb_id, &bot.gff, &mut item, &reference);
+    }
+
     // Apply diff
-    let my_props = vec![
+    let paralog_property = bot.orthologs.paralog_property.clone();
+    let mut my_props = vec![
         "P1343", // Decribed by source; CAREFUL!
         "P703",  // Found in taxon
         "P1057", // Chromosome
@@ -92,23 +111,45 @@ pub fn process(
         "P645",  // Genomic end
         "P680", "P681", "P682",
     ];
+    if bot.gene_structure || bot.protein_fasta_file.is_some() {
+        my_props.push("P1114"); // Quantity (exons, amino acids)
+    }
+    if bot.gene_structure {
+        my_props.push("P2043"); // Length (CDS, transcript)
+    }
+    if bot.protein_fasta_file.is_some() {
+        my_props.push("P2067"); // Mass
+        my_props.push("P4092"); // Checksum
+    }
+    if bot.orthologs.on_proteins() {
+        my_props.push("P684"); // Ortholog
+        if bot.orthologs.process_paralogs {
+            my_props.push(&paralog_property);
+        }
+    }
 
+    // Product names are English; other languages keep their labels, or fall back to the systematic ID
+    bot.set_extra_language_labels(&item_to_diff, &mut item, protein_genedb_id);
+    let languages = bot.languages();
+    let languages: Vec<&str> = languages.iter().map(|l| l.as_str()).collect();
     let mut params = EntityDiffParams::none();
-    params.labels = EntityDiffParam::some(&vec!["en"]);
-    params.descriptions = EntityDiffParam::some(&vec!["en"]);
-    params.aliases = EntityDiffParam::some(&vec!["en"]);
+    params.labels = EntityDiffParam::some(&languages);
+    params.descriptions = EntityDiffParam::some(&languages);
+    params.aliases = EntityDiffParam::some(&languages);
     params.claims.add = EntityDiffParamState::All;
     params.claims.alter = EntityDiffParamState::All;
     params.claims.remove = EntityDiffParamState::some(&my_props);
     params.qualifiers = EntityDiffParamSub::all();
     params.references.list.push((
         EntityDiffParamState::some(&my_props),
-        EntityDiffParamState::except(&vec!["P813"]),
+        bot.retrieved_policy.reference_diff_state(),
     ));
 
+    bot.set_description(&item_to_diff, &mut item, "protein", protein_genedb_id);
+    bot.apply_retrieved_policy(&item_to_diff, &mut item);
     let mut diff = EntityDiff::new(&item_to_diff, &item, &params);
     diff.set_edit_summary(bot.get_edit_summary());
-    protein_edit_validator(&mut diff, &item_to_diff);
+    bot.protect_claims(ItemKind::Protein, &mut diff, &item_to_diff);
     if !diff.is_empty() {
         if bot.verbose {
             println!(
@@ -131,69 +172,16 @@ pub fn process(
 
     // TODO add main subject to literature
 
-    bot.get_entity_id_for_genedb_id(&protein_genedb_id)
-}
-
-/// Blocks removal of claims [GO terms] that have a reference a non-GeneDB curator
-fn protein_edit_validator(diff: &mut EntityDiff, original_item: &Entity) {
-    let actions = diff.actions_mut();
-    if actions["claims"].is_null() {
-        return;
-    }
-    let claim_actions = match actions["claims"].as_array_mut() {
-        Some(c) => c,
-        None => return,
-    };
-    let props = ["P680", "P681", "P682", "P1343"];
-
-    // Closure should return false to remove the action, keeping the statment in the item
-    claim_actions.retain(|action| {
-        // Removals only
-        if !action["remove"].is_string() {
-            return true;
-        }
-        // Get the ID of the claim to be removed
-        let id = match action["id"].as_str() {
-            Some(id) => id,
-            None => return true,
-        };
-        // Find that claim in the original item
-        let claim = match original_item.claim_with_id(id) {
-            Some(claim) => claim,
-            None => return true,
-        };
-        // Not a GO term property?
-        if !props.contains(&claim.main_snak().property()) {
-            return true;
-        }
-
-        // This is a GO term property
-        let references = claim.references();
-        // No reference? => remove statement
-        if references.is_empty() {
-            return true;
-        }
-        // More than one reference block? => do not remove statement
-        if references.len() > 1 {
-            return false;
+    let protein_q = bot.get_entity_id_for_genedb_id(&protein_genedb_id);
+    match &protein_q {
+        Some(q) => {
+            if bot.orthologs.check_reverse() {
+                link_orthologs(bot, &protein_genedb_id, &ortholog_entity_ids, q);
+            }
         }
-        // Any P1640 (=curator) snaks that are NOT Q5531047 (GeneDB)? => do remove statement
-        let non_genedb_curators = references
-            .get(0)
-            .unwrap()
-            .snaks()
-            .iter()
-            .filter(|snak| snak.property() == "P1640")
-            .filter(|snak| match snak.data_value() {
-                Some(dv) => match dv.value() {
-                    Value::Entity(value) => value.id() != "Q5531047",
-                    _ => false,
-                },
-                None => false,
-            })
-            .count();
-        non_genedb_curators == 0
-    });
+        None => {}
+    }
+    protein_q
 }
 
 /// Adds GO annotation to the protein item
@@ -203,9 +191,6 @@ fn add_go_annotation(
     gff: &bio::io::gff::Record,
     literature: &mut HashSet<Literature>,
 ) {
-    lazy_static! {
-        static ref RE_DATE: Regex = Regex::new(r"^(\d{4})(\d{2})(\d{2})$").unwrap();
-    }
     let protein_genedb_id = gff.attributes()["ID"].clone();
     let gaf = match bot.gaf.get(&protein_genedb_id) {
         Some(gaf) => gaf.clone(),
@@ -213,7 +198,10 @@ fn add_go_annotation(
     };
     let mut new_go_claims: HashMap<String, (Snak, Vec<Reference>, Vec<Snak>)> = HashMap::new();
     for ga in gaf {
-        let go_term = ga.go_id().to_string();
+        let go_term = match bot.check_go_term(&protein_genedb_id, &ga.go_id().to_string()) {
+            Some(go_term) => go_term,
+            None => continue,
+        };
         let go_q = match bot.get_item_for_go_term(&go_term) {
             Some(q) => q,
             None => {
@@ -238,8 +226,8 @@ fn add_go_annotation(
         };
 
         let evidence_code = ga.evidence_code().to_string();
-        let evidence_code_q = match bot.evidence.code2q.get(&evidence_code) {
-            Some(q) => q.clone(),
+        let evidence_code_q = match bot.get_evidence_item(&evidence_code) {
+            Some(q) => q,
             None => {
                 bot.log(
                     &protein_genedb_id,
@@ -252,6 +240,53 @@ fn add_go_annotation(
             }
         };
 
+        // NOT and GO relation qualifiers, logged once per GAF row
+        let mut relation_qualifiers = vec![];
+        let mut has_relation = false;
+        for qual in ga.qualifier() {
+            if qual == "NOT" {
+                relation_qualifiers.push(Snak::new_item("P6477", "Q186290"));
+            } else if qual.is_empty() {
+                continue;
+            } else {
+                has_relation = true;
+                match bot.get_go_relation_qualifier(qual) {
+                    Some(snak) => relation_qualifiers.push(snak),
+                    None => bot.log(
+                        &protein_genedb_id,
+                        &format!("Unknown GO relation '{}' for GO term '{}'", qual, &go_term),
+                    ),
+                }
+            }
+        }
+        if !has_relation && bot.gaf_version_at_least(2, 2) {
+            bot.log(
+                &protein_genedb_id,
+                &format!(
+                    "No GO relation for GO term '{}' in GAF {}",
+                    &go_term, &bot.gaf_version
+                ),
+            );
+        }
+
+        // Annotation extensions; each group is a separate context, so a separate statement
+        let extension_groups = get_annotation_extension_qualifiers(bot, &ga, &protein_genedb_id);
+        if extension_groups.is_empty() {
+            bot.log(
+                &protein_genedb_id,
+                &format!(
+                    "No annotation extension resolved for GO term '{}', skipping annotation",
+                    &go_term
+                ),
+            );
+            continue;
+        }
+
+        // Host-pathogen annotation, date and curator, logged once per GAF row
+        let host_qualifiers = bot.get_host_qualifiers(&protein_genedb_id, &ga);
+        let annotation_time = bot.get_time_for_gaf_date(&protein_genedb_id, ga.date());
+        let curator_q = bot.get_curator_for_assigned_by(&protein_genedb_id, ga.assigned_by());
+
         // Literature
         let mut literature_sources: Vec<Snak> = vec![];
         for (k, values) in ga.db_ref().iter_all() {
@@ -281,24 +316,11 @@ fn add_go_annotation(
 
                 // Qualifiers
                 let mut qualifiers = vec![Snak::new_item("P459", &evidence_code_q)];
-                for qual in ga.qualifier() {
-                    if qual == "NOT" {
-                        qualifiers.push(Snak::new_item("P6477", "Q186290"));
-                    }
-                }
+                qualifiers.extend(relation_qualifiers.clone());
 
-                // Date
-                match RE_DATE.captures(ga.date()) {
-                    Some(caps) => {
-                        let time = "+".to_string()
-                            + caps.get(1).unwrap().as_str()
-                            + "-"
-                            + caps.get(2).unwrap().as_str()
-                            + "-"
-                            + caps.get(3).unwrap().as_str()
-                            + "T00:00:00Z";
-                        qualifiers.push(Snak::new_time("P585", &time, 11));
-                    }
+                qualifiers.extend(host_qualifiers.clone());
+                match &annotation_time {
+                    Some(time) => qualifiers.push(Snak::new_time("P585", time, 11)),
                     None => {}
                 }
 
@@ -312,36 +334,50 @@ fn add_go_annotation(
                 }
 
                 // References for GO terms
+                let retrieved = match (bot.gaf_date_as_retrieved, &annotation_time) {
+                    (true, Some(time)) => Snak::new_time("P813", time, 11),
+                    _ => bot.new_time_today(),
+                };
                 let references: Vec<Reference> = literature_sources
                     .iter()
                     .map(|ls| {
-                        Reference::new(vec![
-                            ls.clone(),
-                            Snak::new_item("P1640", "Q5531047"),
-                            bot.new_time_today(),
-                        ])
+                        let mut snaks = vec![ls.clone()];
+                        match &curator_q {
+                            Some(q) => snaks.push(Snak::new_item("P1640", q)),
+                            None => {}
+                        }
+                        match bot.release_version_snak() {
+                            Some(snak) => snaks.push(snak),
+                            None => {}
+                        }
+                        snaks.push(retrieved.clone());
+                        Reference::new(snaks)
                     })
                     .collect();
 
-                let new_claim_key = json!([aspect_p.clone(), go_q.clone(), qualifiers.clone()]);
-                let new_claim_key = serde_json::to_string(&new_claim_key).unwrap();
-
-                if !new_go_claims.contains_key(&new_claim_key) {
-                    new_go_claims.insert(
-                        new_claim_key.clone(),
-                        (
-                            Snak::new_item(aspect_p.clone(), go_q.clone()),
-                            vec![],
-                            qualifiers,
-                        ),
-                    );
-                }
+                for extension_qualifiers in &extension_groups {
+                    let mut qualifiers = qualifiers.clone();
+                    qualifiers.extend(extension_qualifiers.iter().cloned());
+                    let new_claim_key = json!([aspect_p.clone(), go_q.clone(), qualifiers.clone()]);
+                    let new_claim_key = serde_json::to_string(&new_claim_key).unwrap();
+
+                    if !new_go_claims.contains_key(&new_claim_key) {
+                        new_go_claims.insert(
+                            new_claim_key.clone(),
+                            (
+                                Snak::new_item(aspect_p.clone(), go_q.clone()),
+                                vec![],
+                                qualifiers,
+                            ),
+                        );
+                    }
 
-                let ngc = new_go_claims.get_mut(&new_claim_key).unwrap(); // This was just added
-                for r in references {
-                    ngc.1.push(r);
+                    let ngc = new_go_claims.get_mut(&new_claim_key).unwrap(); // This was just added
+                    for r in &references {
+                        ngc.1.push(r.clone());
+                    }
+                    deduplicate_references(&mut ngc.1);
                 }
-                deduplicate_references(&mut ngc.1);
 
                 for (k, values) in ga.db_ref().iter_all() {
                     for v in values {
@@ -385,6 +421,110 @@ fn add_go_annotation(
     });
 }
 
+/// Parses a GAF annotation extension group (column 16) like "occurs_in(CL:0000057),has_input(GeneDB:PF3D7_0100100)"
+/// into (relation,database,ID) tuples. Returns the first part that can not be parsed as an error.
+fn parse_annotation_extension(group: &str) -> Result<Vec<(String, String, String)>, String> {
+    lazy_static! {
+        static ref RE_EXTENSION: Regex =
+            Regex::new(r"^\s*([A-Za-z_]+)\(([^:()]+):([^()]+)\)\s*$").unwrap();
+    }
+    group
+        .split(',')
+        .filter(|part| !part.trim().is_empty())
+        .map(|part| match RE_EXTENSION.captures(part) {
+            Some(caps) => Ok((
+                caps[1].to_string(),
+                caps[2].to_string(),
+                caps[3].to_string(),
+            )),
+            None => Err(part.trim().to_string()),
+        })
+        .collect()
+}
+
+/// Returns the item for an annotation extension target, eg CL:0000057
+fn get_item_for_extension_target(bot: &mut GeneDBot, db: &str, id: &str) -> Option<String> {
+    match db {
+        "GeneDB" => match bot.get_entity_id_for_genedb_id(&id.to_string()) {
+            Some(q) => Some(q),
+            None => bot.get_gene_or_protein_item_for_genedb_id(id),
+        },
+        "GO" => bot.get_item_for_go_term(&format!("{}:{}", db, id)),
+        other => {
+            let (prop, with_prefix) = bot.extension_db2prop.get(other)?.to_owned();
+            if with_prefix {
+                bot.get_item_for_external_id(&prop, &format!("{}:{}", db, id))
+            } else {
+                bot.get_item_for_external_id(&prop, id)
+            }
+        }
+    }
+}
+
+/// Returns the qualifiers for each annotation extension group of a GAF row; a single empty group if the row has no extensions.
+/// Unresolved extensions are logged, and their group is skipped; if no group resolves, the list is empty.
+fn get_annotation_extension_qualifiers(
+    bot: &mut GeneDBot,
+    ga: &bio::io::gaf::Record,
+    protein_genedb_id: &String,
+) -> Vec<Vec<Snak>> {
+    let mut ret: Vec<Vec<Snak>> = vec![];
+    let mut has_extensions = false;
+    for extension in ga.annotation_extension() {
+        for group in extension.split('|') {
+            if group.trim().is_empty() {
+                continue;
+            }
+            has_extensions = true;
+            let parts = match parse_annotation_extension(group) {
+                Ok(parts) => parts,
+                Err(part) => {
+                    bot.log(
+                        protein_genedb_id,
+                        &format!("Can not parse annotation extension '{}'", &part),
+                    );
+                    continue;
+                }
+            };
+            let mut qualifiers: Vec<Snak> = vec![];
+            let mut resolved = true;
+            for (relation, db, id) in parts {
+                let prop = match bot.extension_relation2prop.get(&relation) {
+                    Some(prop) => prop.to_owned(),
+                    None => {
+                        resolved = false;
+                        bot.log(
+                            protein_genedb_id,
+                            &format!("Unknown annotation extension relation '{}'", &relation),
+                        );
+                        continue;
+                    }
+                };
+                match get_item_for_extension_target(bot, &db, &id) {
+                    Some(q) => qualifiers.push(Snak::new_item(prop, q)),
+                    None => {
+                        resolved = false;
+                        bot.log(
+                            protein_genedb_id,
+                            &format!(
+                                "Unresolved annotation extension '{}({}:{})'",
+                                &relation, &db, &id
+                            ),
+                        );
+                    }
+                }
+            }
+            if resolved && !qualifiers.is_empty() {
+                ret.push(qualifiers);
+            }
+        }
+    }
+    if !has_extensions {
+        ret.push(vec![]);
+    }
+    ret
+}
+
 fn deduplicate_references(references: &mut Vec<Reference>) {
     let mut ref_keys: HashSet<String> = HashSet::new();
     references.retain(|r| {
@@ -400,5 +540,33 @@ fn deduplicate_references(references: &mut Vec<Reference>) {
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn test_parse_annotation_extension() {
+        assert_eq!(
+            parse_annotation_extension("occurs_in(CL:0000057),has_input(GeneDB:PF3D7_0100100)"),
+            Ok(vec![
+                (
+                    "occurs_in".to_string(),
+                    "CL".to_string(),
+                    "0000057".to_string()
+                ),
+                (
+                    "has_input".to_string(),
+                    "GeneDB".to_string(),
+                    "PF3D7_0100100".to_string()
+                ),
+            ])
+        );
+        assert_eq!(parse_annotation_extension(""), Ok(vec![]));
+        assert_eq!(
+            parse_annotation_extension("not an extension"),
+            Err("not an extension".to_string())
+        );
+        assert_eq!(
+            parse_annotation_extension("occurs_in(CL:0000057),part_of(foo)"),
+            Err("part_of(foo)".to_string())
+        );
+    }
 }
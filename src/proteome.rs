@@ -0,0 +1,147 @@
+use crate::{GeneDBot, Toolbox};
+use bio::io::fasta;
+use std::collections::HashMap;
+use std::error::Error;
+use wikibase::*;
+
+pub const DALTON_Q: &str = "Q483261";
+pub const MD5_Q: &str = "Q185235";
+pub const CHEBI_AMINO_ACID: &str = "33709";
+
+/// Average residue masses (Da) of the amino acids, as used by ExPASy ProtParam
+const RESIDUE_MASSES: &[(char, f64)] = &[
+    ('A', 71.0788),
+    ('R', 156.1875),
+    ('N', 114.1038),
+    ('D', 115.0886),
+    ('C', 103.1388),
+    ('E', 129.1155),
+    ('Q', 128.1307),
+    ('G', 57.0519),
+    ('H', 137.1411),
+    ('I', 113.1594),
+    ('L', 113.1594),
+    ('K', 128.1741),
+    ('M', 131.1926),
+    ('F', 147.1766),
+    ('P', 97.1167),
+    ('S', 87.0782),
+    ('T', 101.1051),
+    ('W', 186.2132),
+    ('Y', 163.1760),
+    ('V', 99.1326),
+    ('U', 150.0388),
+    ('O', 237.3018),
+];
+const WATER_MASS: f64 = 18.01524;
+
+/// Properties derived from a protein sequence
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProteinSequence {
+    pub length: usize,
+    /// None if the sequence contains ambiguous residues (eg X)
+    pub mass: Option<f64>,
+    pub md5: String,
+}
+
+impl ProteinSequence {
+    /// Trailing stop codons ("*") are removed
+    pub fn new_from_sequence(sequence: &str) -> Self {
+        let sequence = sequence.trim().trim_end_matches('*').to_uppercase();
+        Self {
+            length: sequence.chars().count(),
+            mass: molecular_mass(&sequence),
+            md5: format!("{:x}", md5::compute(sequence.as_bytes())),
+        }
+    }
+}
+
+fn molecular_mass(sequence: &str) -> Option<f64> {
+    if sequence.is_empty() {
+        return None;
+    }
+    let mut mass = WATER_MASS;
+    for residue in sequence.chars() {
+        mass += RESIDUE_MASSES.iter().find(|(r, _)| *r == residue)?.1;
+    }
+    Some((mass * 100.0).round() / 100.0)
+}
+
+/// Parses a protein FASTA file; sequence IDs like "PF3D7_0100100.1:pep" are mapped to the transcript ID
+pub fn load_fasta(data: &[u8]) -> Result<HashMap<String, ProteinSequence>, Box<dyn Error>> {
+    let mut ret = HashMap::new();
+    for record in fasta::Reader::new(data).records() {
+        let record = record?;
+        let id = record.id().split(':').next().unwrap_or("").to_string();
+        if id.is_empty() {
+            continue;
+        }
+        let sequence = String::from_utf8_lossy(record.seq()).to_string();
+        ret.insert(id, ProteinSequence::new_from_sequence(&sequence));
+    }
+    Ok(ret)
+}
+
+/// Adds sequence length (P1114, with unit amino acid), molecular mass (P2067, in Da) and MD5 checksum (P4092) to a protein item
+pub fn add_sequence_statements(
+    bot: &mut GeneDBot,
+    protein_genedb_id: &String,
+    item: &mut Entity,
+    reference: &Reference,
+) {
+    let sequence = match bot.protein_sequences.get(protein_genedb_id) {
+        Some(sequence) => sequence.clone(),
+        None => return,
+    };
+    let mut statements = vec![];
+    match bot.get_item_for_external_id("P683", CHEBI_AMINO_ACID) {
+        Some(q) => statements.push((
+            bot.new_quantity_with_unit("P1114", sequence.length as f64, &q),
+            vec![],
+        )),
+        None => bot.log(protein_genedb_id, "No Wikidata item for amino acid"),
+    }
+    match sequence.mass {
+        Some(mass) => {
+            statements.push((bot.new_quantity_with_unit("P2067", mass, DALTON_Q), vec![]))
+        }
+        None => bot.log(
+            protein_genedb_id,
+            "Protein sequence has ambiguous residues, no molecular mass",
+        ),
+    }
+    statements.push((
+        Snak::new_string("P4092", &sequence.md5),
+        vec![Snak::new_item("P459", MD5_Q)],
+    ));
+    for (snak, qualifiers) in statements {
+        item.add_claim(Statement::new_normal(
+            snak,
+            qualifiers,
+            vec![reference.clone()],
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protein_sequence() {
+        let s = ProteinSequence::new_from_sequence("mg*");
+        assert_eq!(s.length, 2);
+        assert_eq!(s.mass, Some(206.26));
+        assert_eq!(s.md5, format!("{:x}", md5::compute(b"MG")));
+        assert_eq!(ProteinSequence::new_from_sequence("MXG").mass, None);
+    }
+
+    #[test]
+    fn test_load_fasta() {
+        let fasta = ">PF3D7_0100100.1:pep product\nMG\nA*\n>PF3D7_0100200.1\nM\n";
+        let sequences = load_fasta(fasta.as_bytes()).unwrap();
+        assert_eq!(sequences.len(), 2);
+        assert_eq!(sequences["PF3D7_0100100.1"].length, 3);
+        assert_eq!(sequences["PF3D7_0100200.1"].length, 1);
+    }
+}
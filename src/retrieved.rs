@@ -0,0 +1,232 @@
+use chrono::NaiveDate;
+use wikibase::entity_diff::*;
+use wikibase::*;
+
+pub const RETRIEVED: &str = "P813";
+
+/// When the P813 (retrieved) date of a reference is set to today
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetrievedPolicy {
+    /// Whenever the reference is written; P813 alone does not cause an edit
+    Always,
+    /// Only if the reference (without P813) is new or has changed
+    OnChange,
+    /// Like OnChange, but also if the existing date is older than the given number of days
+    MaxAgeDays(i64),
+}
+
+impl RetrievedPolicy {
+    pub fn new_from_str(s: &str) -> Option<Self> {
+        let mut parts = s.trim().splitn(2, ':');
+        match (parts.next()?, parts.next()) {
+            ("always", None) => Some(RetrievedPolicy::Always),
+            ("on_change", None) => Some(RetrievedPolicy::OnChange),
+            ("max_age", Some(days)) => Some(RetrievedPolicy::MaxAgeDays(days.trim().parse().ok()?)),
+            _ => None,
+        }
+    }
+
+    /// Sets the P813 snaks in the references of the new item, based on the original item.
+    /// A new P813 that is not today was taken from the source (eg the GAF annotation date), and is always kept.
+    pub fn apply(&self, original_item: &Entity, item: &mut Entity, today: NaiveDate) {
+        if *self == RetrievedPolicy::Always {
+            return;
+        }
+        for statement in item.claims_mut() {
+            let original_references: Vec<Reference> = original_item
+                .claims()
+                .iter()
+                .filter(|claim| claim.main_snak() == statement.main_snak())
+                .flat_map(|claim| claim.references().iter().cloned())
+                .collect();
+            if original_references.is_empty() {
+                continue;
+            }
+            let references: Vec<Reference> = statement
+                .references()
+                .iter()
+                .map(|reference| {
+                    match get_retrieved_date(reference) {
+                        Some(date) if date != today => return reference.clone(),
+                        _ => {}
+                    }
+                    match original_references
+                        .iter()
+                        .find(|r| is_same_without_retrieved(r, reference))
+                    {
+                        Some(original) => {
+                            if self.is_too_old(original, today) {
+                                reference.clone()
+                            } else {
+                                original.clone()
+                            }
+                        }
+                        None => reference.clone(),
+                    }
+                })
+                .collect();
+            statement.set_references(references);
+        }
+    }
+
+    /// Which reference properties are compared when diffing.
+    /// With `Always`, P813 is ignored, so today's date is only written along with an otherwise changed reference.
+    /// The other policies have already reset unchanged references to the original date.
+    pub fn reference_diff_state(&self) -> EntityDiffParamState {
+        match self {
+            RetrievedPolicy::Always => EntityDiffParamState::except(&vec![RETRIEVED]),
+            _ => EntityDiffParamState::All,
+        }
+    }
+
+    fn is_too_old(&self, reference: &Reference, today: NaiveDate) -> bool {
+        let max_age = match self {
+            RetrievedPolicy::MaxAgeDays(days) => *days,
+            _ => return false,
+        };
+        match get_retrieved_date(reference) {
+            Some(date) => (today - date).num_days() > max_age,
+            None => true,
+        }
+    }
+}
+
+fn snaks_without_retrieved(reference: &Reference) -> Vec<&Snak> {
+    reference
+        .snaks()
+        .iter()
+        .filter(|snak| snak.property() != RETRIEVED)
+        .collect()
+}
+
+/// Compares two references, ignoring P813
+fn is_same_without_retrieved(r1: &Reference, r2: &Reference) -> bool {
+    let s1 = snaks_without_retrieved(r1);
+    let s2 = snaks_without_retrieved(r2);
+    s1.len() == s2.len() && s1.iter().all(|snak| s2.contains(snak))
+}
+
+fn get_retrieved_date(reference: &Reference) -> Option<NaiveDate> {
+    let snak = reference
+        .snaks()
+        .iter()
+        .find(|snak| snak.property() == RETRIEVED)?;
+    match snak.data_value().as_ref()?.value() {
+        Value::Time(t) => NaiveDate::parse_from_str(t.time().get(1..11)?, "%Y-%m-%d").ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(date: &str) -> Reference {
+        Reference::new(vec![
+            Snak::new_item("P248", "Q5531047"),
+            Snak::new_time(RETRIEVED, &format!("+{}T00:00:00Z", date), 11),
+        ])
+    }
+
+    fn item_with_reference(reference: Reference) -> Entity {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(Statement::new_normal(
+            Snak::new_item("P31", "Q7187"),
+            vec![],
+            vec![reference],
+        ));
+        item
+    }
+
+    fn get_date(item: &Entity) -> Option<NaiveDate> {
+        get_retrieved_date(&item.claims()[0].references()[0])
+    }
+
+    #[test]
+    fn test_new_from_str() {
+        assert_eq!(
+            RetrievedPolicy::new_from_str("always"),
+            Some(RetrievedPolicy::Always)
+        );
+        assert_eq!(
+            RetrievedPolicy::new_from_str("on_change"),
+            Some(RetrievedPolicy::OnChange)
+        );
+        assert_eq!(
+            RetrievedPolicy::new_from_str("max_age:30"),
+            Some(RetrievedPolicy::MaxAgeDays(30))
+        );
+        assert_eq!(RetrievedPolicy::new_from_str("max_age:x"), None);
+        assert_eq!(RetrievedPolicy::new_from_str("sometimes"), None);
+    }
+
+    #[test]
+    fn test_apply() {
+        let today = NaiveDate::from_ymd_opt(2020, 3, 1).unwrap();
+        let original = item_with_reference(reference("2020-01-01"));
+        let new_item = item_with_reference(reference("2020-03-01"));
+
+        let mut item = new_item.clone();
+        RetrievedPolicy::Always.apply(&original, &mut item, today);
+        assert_eq!(get_date(&item), Some(today));
+
+        let mut item = new_item.clone();
+        RetrievedPolicy::OnChange.apply(&original, &mut item, today);
+        assert_eq!(get_date(&item), NaiveDate::from_ymd_opt(2020, 1, 1));
+
+        let mut item = new_item.clone();
+        RetrievedPolicy::MaxAgeDays(90).apply(&original, &mut item, today);
+        assert_eq!(get_date(&item), NaiveDate::from_ymd_opt(2020, 1, 1));
+
+        let mut item = new_item.clone();
+        RetrievedPolicy::MaxAgeDays(30).apply(&original, &mut item, today);
+        assert_eq!(get_date(&item), Some(today));
+
+        // Changed reference
+        let changed = Reference::new(vec![
+            Snak::new_item("P248", "Q1"),
+            Snak::new_time(RETRIEVED, "+2020-03-01T00:00:00Z", 11),
+        ]);
+        let mut item = item_with_reference(changed);
+        RetrievedPolicy::OnChange.apply(&original, &mut item, today);
+        assert_eq!(get_date(&item), Some(today));
+
+        // Date from the source, not today
+        let mut item = item_with_reference(reference("2015-06-01"));
+        RetrievedPolicy::OnChange.apply(&original, &mut item, today);
+        assert_eq!(get_date(&item), NaiveDate::from_ymd_opt(2015, 6, 1));
+    }
+
+    fn diff_is_empty(policy: &RetrievedPolicy, original: &Entity, item: &Entity) -> bool {
+        let mut params = EntityDiffParams::none();
+        params.claims.add = EntityDiffParamState::All;
+        params.claims.alter = EntityDiffParamState::All;
+        params.claims.remove = EntityDiffParamState::some(&vec!["P31"]);
+        params.references.list.push((
+            EntityDiffParamState::some(&vec!["P31"]),
+            policy.reference_diff_state(),
+        ));
+        EntityDiff::new(original, item, &params).is_empty()
+    }
+
+    #[test]
+    fn test_unchanged_item_has_empty_diff() {
+        let today = NaiveDate::from_ymd_opt(2020, 3, 1).unwrap();
+        let original = item_with_reference(reference("2020-02-20"));
+        for policy in &[
+            RetrievedPolicy::Always,
+            RetrievedPolicy::OnChange,
+            RetrievedPolicy::MaxAgeDays(30),
+        ] {
+            let mut item = item_with_reference(reference("2020-03-01"));
+            policy.apply(&original, &mut item, today);
+            assert!(diff_is_empty(policy, &original, &item), "{:?}", policy);
+        }
+
+        // Too old for the policy
+        let mut item = item_with_reference(reference("2020-03-01"));
+        let policy = RetrievedPolicy::MaxAgeDays(5);
+        policy.apply(&original, &mut item, today);
+        assert!(!diff_is_empty(&policy, &original, &item));
+    }
+}
@@ -0,0 +1,243 @@
+use regex::Regex;
+use wikibase::*;
+
+pub const ENA_URL: &str = "https://www.ebi.ac.uk/ena/browser/view/";
+pub const DEFAULT_SEQUENCE_TYPE: &str = "chromosome";
+pub const CHROMOSOME_Q: &str = "Q37748";
+
+/// Sequence type => Sequence Ontology ID of its class
+pub const SEQUENCE_TYPES: &[(&str, &str)] = &[
+    ("chromosome", "SO:0000340"),
+    ("contig", "SO:0000149"),
+    ("supercontig", "SO:0000148"),
+    ("scaffold", "SO:0000148"),
+    ("mitochondrial_chromosome", "SO:0000737"),
+    ("apicoplast_chromosome", "SO:0001259"),
+    ("chloroplast_chromosome", "SO:0000820"),
+];
+
+/// The Sequence Ontology ID for a sequence type; types can also be given as SO IDs directly
+pub fn get_so_id(sequence_type: &str) -> Option<String> {
+    if sequence_type.starts_with("SO:") {
+        return Some(sequence_type.to_string());
+    }
+    SEQUENCE_TYPES
+        .iter()
+        .find(|(t, _)| *t == sequence_type)
+        .map(|(_, so)| so.to_string())
+}
+
+/// Sets the type of the sequences whose names match the pattern
+#[derive(Debug, Clone)]
+pub struct SequenceTypeRule {
+    pub pattern: Regex,
+    pub sequence_type: String,
+}
+
+impl SequenceTypeRule {
+    /// Parses "PATTERN=TYPE", eg "_MIT=mitochondrial_chromosome" or "maxi=SO:0000980"
+    pub fn new_from_str(s: &str) -> Option<Self> {
+        let pos = s.rfind('=')?;
+        let (pattern, sequence_type) = (s[..pos].trim(), s[pos + 1..].trim());
+        if pattern.is_empty() || get_so_id(sequence_type).is_none() {
+            return None;
+        }
+        Some(Self {
+            pattern: Regex::new(pattern).ok()?,
+            sequence_type: sequence_type.to_string(),
+        })
+    }
+
+    /// Parses rules separated by ";"; returns the rules, and the rule strings that could not be parsed
+    pub fn new_list_from_str(s: &str) -> (Vec<Self>, Vec<String>) {
+        let mut rules = vec![];
+        let mut errors = vec![];
+        for rule in s.split(';').filter(|r| !r.trim().is_empty()) {
+            match SequenceTypeRule::new_from_str(rule) {
+                Some(r) => rules.push(r),
+                None => errors.push(rule.trim().to_string()),
+            }
+        }
+        (rules, errors)
+    }
+}
+
+/// The type of a sequence: from the first matching rule, the type of its GFF record, or chromosome
+pub fn infer_sequence_type(
+    seqname: &str,
+    region: Option<&SequenceRegion>,
+    rules: &Vec<SequenceTypeRule>,
+) -> String {
+    match rules.iter().find(|rule| rule.pattern.is_match(seqname)) {
+        Some(rule) => return rule.sequence_type.to_owned(),
+        None => {}
+    }
+    match region.and_then(|r| r.feature_type.as_ref()) {
+        Some(feature_type) => {
+            if get_so_id(feature_type).is_some() {
+                return feature_type.to_owned();
+            }
+        }
+        None => {}
+    }
+    DEFAULT_SEQUENCE_TYPE.to_string()
+}
+
+/// A sequence (chromosome, contig...) of the genome, from the GFF "##sequence-region" directive and its GFF record
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceRegion {
+    pub start: u64,
+    pub end: u64,
+    pub feature_type: Option<String>,
+    pub dbxrefs: Vec<String>,
+}
+
+impl SequenceRegion {
+    pub fn new(start: u64, end: u64) -> Self {
+        Self {
+            start,
+            end,
+            feature_type: None,
+            dbxrefs: vec![],
+        }
+    }
+
+    /// Parses "##sequence-region SEQID START END"; returns the sequence ID and the region
+    pub fn new_from_directive(line: &str) -> Option<(String, Self)> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 4 || parts[0] != "##sequence-region" {
+            return None;
+        }
+        Some((
+            parts[1].to_string(),
+            Self::new(parts[2].parse().ok()?, parts[3].parse().ok()?),
+        ))
+    }
+
+    /// Adds type and Dbxref of the GFF record of the sequence; the directive takes precedence for start/end
+    pub fn update_from_record(&mut self, record: &bio::io::gff::Record, has_directive: bool) {
+        if !has_directive {
+            self.start = *record.start();
+            self.end = *record.end();
+        }
+        self.feature_type = Some(record.feature_type().to_string());
+        match record.attributes().get_vec("Dbxref") {
+            Some(xrefs) => xrefs
+                .iter()
+                .flat_map(|xrefs| xrefs.split(','))
+                .map(|xref| xref.trim().to_string())
+                .filter(|xref| !xref.is_empty())
+                .for_each(|xref| {
+                    if !self.dbxrefs.contains(&xref) {
+                        self.dbxrefs.push(xref)
+                    }
+                }),
+            None => {}
+        }
+    }
+
+    pub fn length(&self) -> u64 {
+        if self.end >= self.start {
+            self.end - self.start + 1
+        } else {
+            self.start - self.end + 1
+        }
+    }
+
+    /// RefSeq accessions as P2249 (RefSeq genome ID), INSDC accessions as P973 (described at URL) to ENA
+    pub fn accession_snaks(&self) -> Vec<Snak> {
+        self.dbxrefs
+            .iter()
+            .filter_map(|xref| {
+                let mut parts = xref.splitn(2, ':');
+                match (parts.next()?, parts.next()?) {
+                    ("RefSeq", acc) => Some(Snak::new_string("P2249", acc)),
+                    ("INSDC", acc)
+                    | ("EMBL", acc)
+                    | ("GenBank", acc)
+                    | ("ENA", acc)
+                    | ("DDBJ", acc) => {
+                        Some(Snak::new_string("P973", &format!("{}{}", ENA_URL, acc)))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bio::io::gff;
+
+    #[test]
+    fn test_sequence_type_rule() {
+        let rule = SequenceTypeRule::new_from_str("_MIT$=mitochondrial_chromosome").unwrap();
+        assert!(rule.pattern.is_match("Pf3D7_MIT"));
+        assert_eq!(rule.sequence_type, "mitochondrial_chromosome");
+        assert!(SequenceTypeRule::new_from_str("maxi=SO:0000980").is_some());
+        assert!(SequenceTypeRule::new_from_str("maxi=foo").is_none());
+        assert!(SequenceTypeRule::new_from_str("=contig").is_none());
+        assert!(SequenceTypeRule::new_from_str("(=contig").is_none());
+        let (rules, errors) = SequenceTypeRule::new_list_from_str(
+            "_MIT$=mitochondrial_chromosome;_API$=apicoplast_chromosome;x=y",
+        );
+        assert_eq!(rules.len(), 2);
+        assert_eq!(errors, vec!["x=y"]);
+    }
+
+    #[test]
+    fn test_infer_sequence_type() {
+        let (rules, _) = SequenceTypeRule::new_list_from_str("_MIT$=mitochondrial_chromosome");
+        let mut region = SequenceRegion::new(1, 100);
+        assert_eq!(
+            infer_sequence_type("Pf3D7_MIT", None, &rules),
+            "mitochondrial_chromosome"
+        );
+        assert_eq!(infer_sequence_type("Pf3D7_01", None, &rules), "chromosome");
+        region.feature_type = Some("contig".to_string());
+        assert_eq!(infer_sequence_type("c1", Some(&region), &rules), "contig");
+        region.feature_type = Some("region".to_string());
+        assert_eq!(
+            infer_sequence_type("c1", Some(&region), &rules),
+            "chromosome"
+        );
+    }
+
+    #[test]
+    fn test_new_from_directive() {
+        let (id, region) =
+            SequenceRegion::new_from_directive("##sequence-region   Pf3D7_01_v3 1 640851").unwrap();
+        assert_eq!(id, "Pf3D7_01_v3");
+        assert_eq!(region.length(), 640851);
+        assert_eq!(
+            SequenceRegion::new_from_directive("##sequence-region Pf3D7_01_v3 1"),
+            None
+        );
+        assert_eq!(
+            SequenceRegion::new_from_directive("##gff-version 3 1 2"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_update_from_record() {
+        let gff = "Pf3D7_01_v3\tchado\tchromosome\t1\t1000\t.\t.\t.\tID=Pf3D7_01_v3;Dbxref=RefSeq:NC_004325.2,INSDC:LN999943.1,Foo:1\n";
+        let mut reader = gff::Reader::new(gff.as_bytes(), gff::GffType::GFF3);
+        let record = reader.records().next().unwrap().unwrap();
+        let mut region = SequenceRegion::new(1, 640851);
+        region.update_from_record(&record, true);
+        assert_eq!(region.length(), 640851);
+        assert_eq!(region.feature_type, Some("chromosome".to_string()));
+        assert_eq!(
+            region.accession_snaks(),
+            vec![
+                Snak::new_string("P2249", "NC_004325.2"),
+                Snak::new_string("P973", &format!("{}LN999943.1", ENA_URL)),
+            ]
+        );
+        region.update_from_record(&record, false);
+        assert_eq!(region.length(), 1000);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{GeneDBot, Toolbox};
+use std::collections::HashMap;
+use wikibase::*;
+
+pub const SO_EXON: &str = "SO:0000147";
+pub const SO_CDS: &str = "SO:0000316";
+pub const SO_TRANSCRIPT: &str = "SO:0000673";
+/// Unit of sequence lengths
+pub const SO_BASE_PAIR: &str = "SO:0000028";
+
+/// Gene model of a transcript, from its GFF children
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptStructure {
+    pub exon_count: usize,
+    pub cds_length: u64,
+    pub span: u64,
+}
+
+impl TranscriptStructure {
+    /// Builds the structure of a transcript from the parent2child tree. Without exon features, CDS features are counted as exons.
+    pub fn new_from_gff(
+        transcript_id: &String,
+        gff: &HashMap<String, bio::io::gff::Record>,
+        parent2child: &HashMap<String, Vec<(String, String)>>,
+    ) -> Option<Self> {
+        let transcript = gff.get(transcript_id)?;
+        let children = parent2child.get(transcript_id)?;
+        let records_of_type = |feature_type: &str| -> Vec<&bio::io::gff::Record> {
+            children
+                .iter()
+                .filter(|(_, child_type)| child_type == feature_type)
+                .filter_map(|(child_id, _)| gff.get(child_id))
+                .collect()
+        };
+        let exons = records_of_type("exon");
+        let cds = records_of_type("CDS");
+        let exon_count = if exons.is_empty() {
+            cds.len()
+        } else {
+            exons.len()
+        };
+        if exon_count == 0 {
+            return None;
+        }
+        Some(Self {
+            exon_count,
+            cds_length: cds.iter().map(|r| feature_length(r)).sum(),
+            span: feature_length(transcript),
+        })
+    }
+}
+
+fn feature_length(record: &bio::io::gff::Record) -> u64 {
+    let (start, end) = (*record.start(), *record.end());
+    if end >= start {
+        end - start + 1
+    } else {
+        start - end + 1
+    }
+}
+
+/// Adds exon count (P1114, in exons, applies to exon), CDS length and transcript span (P2043 in base pairs, applies to CDS/transcript) to an item
+pub fn add_structure_statements(
+    bot: &mut GeneDBot,
+    transcript_id: &String,
+    item: &mut Entity,
+    reference: &Reference,
+) {
+    let structure =
+        match TranscriptStructure::new_from_gff(transcript_id, &bot.gff, &bot.parent2child) {
+            Some(structure) => structure,
+            None => return,
+        };
+    let mut statements = vec![];
+    match bot.get_item_for_external_id("P3986", SO_EXON) {
+        Some(q) => statements.push((
+            bot.new_quantity_with_unit("P1114", structure.exon_count as f64, &q),
+            vec![Snak::new_item("P518", &q)],
+        )),
+        None => bot.log(transcript_id, "No Wikidata item for exon"),
+    }
+    let base_pair_q = bot.get_item_for_external_id("P3986", SO_BASE_PAIR);
+    if base_pair_q.is_none() {
+        bot.log(transcript_id, "No Wikidata item for base pair");
+    }
+    let lengths = vec![
+        (SO_CDS, "CDS", structure.cds_length),
+        (SO_TRANSCRIPT, "transcript", structure.span),
+    ];
+    for (so_id, name, length) in lengths {
+        let base_pair_q = match &base_pair_q {
+            Some(q) => q,
+            None => break,
+        };
+        if length == 0 {
+            continue;
+        }
+        match bot.get_item_for_external_id("P3986", so_id) {
+            Some(q) => statements.push((
+                bot.new_quantity_with_unit("P2043", length as f64, base_pair_q),
+                vec![Snak::new_item("P518", &q)],
+            )),
+            None => bot.log(transcript_id, &format!("No Wikidata item for {}", name)),
+        }
+    }
+    for (snak, qualifiers) in statements {
+        item.add_claim(Statement::new_normal(
+            snak,
+            qualifiers,
+            vec![reference.clone()],
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bio::io::gff;
+
+    fn gff_records(s: &str) -> HashMap<String, gff::Record> {
+        let mut reader = gff::Reader::new(s.as_bytes(), gff::GffType::GFF3);
+        reader
+            .records()
+            .filter_map(|r| r.ok())
+            .map(|r| (r.attributes()["ID"].clone(), r))
+            .collect()
+    }
+
+    #[test]
+    fn test_transcript_structure() {
+        let gff = gff_records("chr1\tchado\tmRNA\t100\t999\t.\t+\t.\tID=T1;Parent=G1\nchr1\tchado\texon\t100\t199\t.\t+\t.\tID=T1:exon:1;Parent=T1\nchr1\tchado\texon\t500\t999\t.\t+\t.\tID=T1:exon:2;Parent=T1\nchr1\tchado\tCDS\t150\t199\t.\t+\t0\tID=T1:CDS:1;Parent=T1\nchr1\tchado\tCDS\t500\t899\t.\t+\t1\tID=T1:CDS:2;Parent=T1\n");
+        let mut parent2child: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        gff.values()
+            .filter(|r| r.attributes().contains_key("Parent"))
+            .for_each(|r| {
+                parent2child
+                    .entry(r.attributes()["Parent"].clone())
+                    .or_insert(vec![])
+                    .push((r.attributes()["ID"].clone(), r.feature_type().to_string()))
+            });
+        let s = TranscriptStructure::new_from_gff(&"T1".to_string(), &gff, &parent2child).unwrap();
+        assert_eq!(s.exon_count, 2);
+        assert_eq!(s.cds_length, 450);
+        assert_eq!(s.span, 900);
+        assert_eq!(
+            TranscriptStructure::new_from_gff(&"T2".to_string(), &gff, &parent2child),
+            None
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use crate::protection::ItemKind;
+use crate::structure;
+use crate::{GeneDBot, Toolbox};
+use regex::Regex;
+use wikibase::entity_diff::*;
+use wikibase::*;
+
+/// GFF transcript type => Sequence Ontology ID of its class
+pub const TRANSCRIPT_TYPES: &[(&str, &str)] = &[
+    ("mRNA", "SO:0000234"),
+    ("pseudogenic_transcript", "SO:0000516"),
+];
+
+pub fn get_so_id(feature_type: &str) -> Option<&'static str> {
+    TRANSCRIPT_TYPES
+        .iter()
+        .find(|(t, _)| *t == feature_type)
+        .map(|(_, so)| *so)
+}
+
+/// The isoform number of a transcript, from the ".N" suffix of its GeneDB ID
+pub fn isoform_number(transcript_genedb_id: &str) -> Option<u32> {
+    lazy_static! {
+        static ref RE_ISOFORM: Regex =
+            Regex::new(r"\.(\d+)$").expect("isoform_number: RE_ISOFORM does not compile");
+    }
+    RE_ISOFORM
+        .captures(transcript_genedb_id)?
+        .get(1)?
+        .as_str()
+        .parse()
+        .ok()
+}
+
+/// Creates or updates the transcript items of a gene; returns the transcript items with their isoform numbers
+pub fn process_transcripts(
+    bot: &mut GeneDBot,
+    gene_genedb_id: &String,
+) -> Vec<(String, Option<u32>)> {
+    let transcript_genedb_ids: Vec<String> = bot
+        .parent2child
+        .get(gene_genedb_id)
+        .unwrap_or(&vec![])
+        .iter()
+        .filter(|child| get_so_id(&child.1).is_some())
+        .map(|child| child.0.to_owned())
+        .collect();
+    transcript_genedb_ids
+        .iter()
+        .filter_map(|transcript_genedb_id| {
+            let transcript_q = process(bot, gene_genedb_id, transcript_genedb_id)?;
+            Some((transcript_q, isoform_number(transcript_genedb_id)))
+        })
+        .collect()
+}
+
+pub fn process(
+    bot: &mut GeneDBot,
+    gene_genedb_id: &String,
+    transcript_genedb_id: &String,
+) -> Option<String> {
+    let gff = match bot.gff.get(transcript_genedb_id) {
+        Some(gff) => gff.clone(),
+        None => return None,
+    };
+    let class_q = match get_so_id(gff.feature_type()) {
+        Some(so_id) => match bot.get_item_for_external_id("P3986", so_id) {
+            Some(q) => q,
+            None => {
+                bot.log(
+                    transcript_genedb_id,
+                    &format!("No Wikidata item for {}", gff.feature_type()),
+                );
+                return None;
+            }
+        },
+        None => {
+            bot.log(
+                transcript_genedb_id,
+                &format!("Transcript has unknown type {}", gff.feature_type()),
+            );
+            return None;
+        }
+    };
+
+    let mut item = Entity::new_empty_item();
+    let item_to_diff = match bot.transcript_genedb2q.get(transcript_genedb_id).cloned() {
+        Some(q) => match bot.ec.load_entity(&bot.api.read().unwrap(), q) {
+            Ok(i) => i.clone(),
+            Err(_) => Entity::new_empty_item(),
+        },
+        None => Entity::new_empty_item(),
+    };
+
+    let reference = bot.genedb_reference(gene_genedb_id);
+    let languages = bot.languages();
+    for language in &languages {
+        item.set_label(LocaleString::new(language, transcript_genedb_id));
+    }
+
+    let mut statements_to_create = vec![
+        Snak::new_item("P31", &class_q), // Instance of:mRNA/pseudogenic transcript
+        Snak::new_item("P703", &bot.species_q()), // Found in:Species
+    ];
+
+    // Part of:gene
+    match bot.get_entity_id_for_genedb_id(gene_genedb_id) {
+        Some(q) => statements_to_create.push(Snak::new_item("P361", &q)),
+        None => {}
+    }
+
+    // Encodes:protein (protein items share the GeneDB ID of their transcript)
+    match bot.get_entity_id_for_genedb_id(transcript_genedb_id) {
+        Some(q) => statements_to_create.push(Snak::new_item("P688", &q)),
+        None => {}
+    }
+
+    statements_to_create.iter().for_each(|s| {
+        item.add_claim(Statement::new_normal(
+            s.to_owned(),
+            vec![],
+            vec![reference.clone()],
+        ))
+    });
+
+    if bot.gene_structure {
+        structure::add_structure_statements(bot, transcript_genedb_id, &mut item, &reference);
+    }
+
+    let my_props = vec![
+        "P31",   // Instance of
+        "P703",  // Found in taxon
+        "P361",  // Part of
+        "P688",  // Encodes
+        "P1114", // Quantity (exons)
+        "P2043", // Length (CDS, transcript)
+        "P3382", // GeneDB ID, belongs to the protein item; removed from transcript items
+    ];
+    let languages: Vec<&str> = languages.iter().map(|l| l.as_str()).collect();
+    let mut params = EntityDiffParams::none();
+    params.labels = EntityDiffParam::some(&languages);
+    params.descriptions = EntityDiffParam::some(&languages);
+    params.claims.add = EntityDiffParamState::All;
+    params.claims.alter = EntityDiffParamState::All;
+    params.claims.remove = EntityDiffParamState::some(&my_props);
+    params.qualifiers = EntityDiffParamSub::all();
+    params.references.list.push((
+        EntityDiffParamState::some(&my_props),
+        bot.retrieved_policy.reference_diff_state(),
+    ));
+
+    bot.set_description(
+        &item_to_diff,
+        &mut item,
+        &gff.feature_type().to_lowercase(),
+        transcript_genedb_id,
+    );
+    bot.apply_retrieved_policy(&item_to_diff, &mut item);
+    let mut diff = EntityDiff::new(&item_to_diff, &item, &params);
+    diff.set_edit_summary(bot.get_edit_summary());
+    bot.protect_claims(ItemKind::Transcript, &mut diff, &item_to_diff);
+    if !diff.is_empty() {
+        if bot.verbose {
+            println!(
+                "\nTRANSCRIPT {}/{:?}:\n{}",
+                &transcript_genedb_id,
+                diff.edit_target(),
+                serde_json::to_string_pretty(&diff.actions()).unwrap()
+            );
+        }
+        if !bot.simulate {
+            match bot.ec.apply_diff(&mut bot.api.write().unwrap(), &diff) {
+                Some(q) => {
+                    bot.transcript_genedb2q
+                        .insert(transcript_genedb_id.to_string(), q);
+                }
+                None => bot.log(transcript_genedb_id, "Applying diff returned nothing"),
+            }
+        }
+    }
+
+    bot.transcript_genedb2q.get(transcript_genedb_id).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_so_id() {
+        assert_eq!(get_so_id("mRNA"), Some("SO:0000234"));
+        assert_eq!(get_so_id("pseudogenic_transcript"), Some("SO:0000516"));
+        assert_eq!(get_so_id("tRNA"), None);
+    }
+
+    #[test]
+    fn test_isoform_number() {
+        assert_eq!(isoform_number("PF3D7_0100100.1"), Some(1));
+        assert_eq!(isoform_number("PF3D7_0100100.12"), Some(12));
+        assert_eq!(isoform_number("PF3D7_0100100"), None);
+        assert_eq!(isoform_number("PF3D7_0100100.1:pep"), None);
+    }
+}